@@ -0,0 +1,64 @@
+//! Setup shared by the benchmarks. Like the OpenGL tests, they run on the main thread which owns a
+//! headless context, and are skipped when no context can be created.
+
+use std::time::Instant;
+
+use eyre::Result;
+use glutin::{dpi::PhysicalSize, event_loop::EventLoop, Api, ContextBuilder, GlProfile, GlRequest};
+use violette::gl;
+
+/// Run the benchmarks with a current OpenGL context, exiting with an error if any of them fails.
+pub fn with_context(benches: impl FnOnce() -> Result<()>) {
+    let Ok(event_loop) = std::panic::catch_unwind(EventLoop::new) else {
+        eprintln!("Skipping benchmarks: no display available");
+        return;
+    };
+    let context = match ContextBuilder::new()
+        .with_gl(GlRequest::Specific(Api::OpenGl, (4, 5)))
+        .with_gl_profile(GlProfile::Core)
+        .build_headless(&event_loop, PhysicalSize::new(1, 1))
+    {
+        Ok(context) => context,
+        Err(err) => {
+            eprintln!("Skipping benchmarks: cannot create a context: {}", err);
+            return;
+        }
+    };
+    let context = match unsafe { context.make_current() } {
+        Ok(context) => context,
+        Err((_, err)) => {
+            eprintln!(
+                "Skipping benchmarks: cannot make the context current: {}",
+                err
+            );
+            return;
+        }
+    };
+    violette::load_with(|name| context.get_proc_address(name));
+
+    if let Err(err) = benches() {
+        eprintln!("Benchmark failed: {:?}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Time `iterations` runs of the closure, after a short warm-up, and print the mean time per run.
+/// The GL command queue is drained before and after so that only the measured runs are counted.
+pub fn bench(name: &str, iterations: u32, mut run: impl FnMut() -> Result<()>) -> Result<()> {
+    for _ in 0..iterations / 10 {
+        run()?;
+    }
+    unsafe { gl::Finish() };
+    let start = Instant::now();
+    for _ in 0..iterations {
+        run()?;
+    }
+    unsafe { gl::Finish() };
+    let elapsed = start.elapsed();
+    println!(
+        "{:<32} {:>10.1} ns/iter",
+        name,
+        elapsed.as_nanos() as f64 / iterations as f64
+    );
+    Ok(())
+}
@@ -0,0 +1,104 @@
+//! Overhead of the logging on hot paths (bindings, uniform writes and draw calls). Compare
+//! `cargo bench --bench hot_paths` with `cargo bench --bench hot_paths --features fast`, which
+//! compiles the logging out.
+//!
+//! A subscriber accepting every event is installed, as an application tracing at `trace` level
+//! would, so that the logging is not skipped at runtime.
+
+mod common;
+
+use std::fmt::{self, Write as _};
+
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+use violette::{
+    base::resource::Resource,
+    buffer::ArrayBuffer,
+    framebuffer::Framebuffer,
+    program::Program,
+    texture::{Dimension, Texture},
+    vertex::{DrawMode, VertexArray},
+};
+
+const ITERATIONS: u32 = 100_000;
+
+/// Formats the fields of every event, then discards them.
+struct FormattingSubscriber;
+
+struct DiscardVisitor(String);
+
+impl Visit for DiscardVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.clear();
+        let _ = write!(self.0, "{} = {:?}", field.name(), value);
+    }
+}
+
+impl Subscriber for FormattingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        event.record(&mut DiscardVisitor(String::new()));
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+fn main() {
+    tracing::subscriber::set_global_default(FormattingSubscriber)
+        .expect("No other subscriber is installed");
+    common::with_context(|| {
+        let buffer = ArrayBuffer::with_data(&[0f32; 4])?;
+        common::bench("buffer bind", ITERATIONS, || {
+            buffer.bind();
+            buffer.unbind();
+            Ok(())
+        })?;
+
+        let program = Program::from_sources(
+            r#"#version 330 core
+void main() {
+    gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+}
+"#,
+            r#"#version 330 core
+uniform vec4 u_color;
+out vec4 color;
+void main() {
+    color = u_color;
+}
+"#,
+            None::<&str>,
+        )?;
+        let location = program.require_uniform("u_color")?;
+        common::bench("set_uniform", ITERATIONS, || {
+            program.set_uniform(location, [1f32, 0.5, 0.25, 1.0])
+        })?;
+
+        let one = std::num::NonZeroU32::new(1).unwrap();
+        let texture = Texture::<[f32; 4]>::new(one, one, one, Dimension::D2);
+        texture.reserve_memory()?;
+        let fbo = Framebuffer::builder()
+            .with_color(0, texture.mipmap(0)?)?
+            .build()?;
+        let vao = VertexArray::new();
+        common::bench("draw", ITERATIONS, || {
+            fbo.draw(&program, &vao, DrawMode::Points, 0..1)
+        })?;
+        Ok(())
+    });
+}
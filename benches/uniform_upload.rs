@@ -0,0 +1,31 @@
+//! Cost of padding elements of uniform buffers to the uniform buffer offset alignment on upload,
+//! which goes through a per-thread scratch buffer. Array buffer updates of the same data, which
+//! are uploaded as-is, are the baseline.
+//!
+//! Small uploads reuse the scratch buffer; uploads larger than its retained capacity allocate on
+//! every call.
+
+mod common;
+
+use violette::buffer::{ArrayBuffer, UniformBuffer};
+
+const ITERATIONS: u32 = 10_000;
+
+fn main() {
+    common::with_context(|| {
+        for len in [16, 4096] {
+            let data = vec![[1f32, 0.5, 0.25, 1.0]; len];
+
+            let mut array = ArrayBuffer::with_data(&data)?;
+            common::bench(&format!("array update ({})", len), ITERATIONS, || {
+                array.update(0, &data)
+            })?;
+
+            let mut uniform = UniformBuffer::with_data(&data)?;
+            common::bench(&format!("uniform update ({})", len), ITERATIONS, || {
+                uniform.update(0, &data)
+            })?;
+        }
+        Ok(())
+    });
+}
@@ -2,12 +2,15 @@ use duplicate::duplicate_item;
 use gl::types::*;
 
 pub mod resource;
+pub mod send;
 
 pub trait GlType {
     const GL_TYPE: GLenum;
     const NUM_COMPONENTS: usize;
     const NORMALIZED: bool;
     const STRIDE: usize;
+    /// Number of columns, for matrix types taking several vertex attribute locations.
+    const COLUMNS: usize = 1;
 }
 
 #[duplicate_item(
@@ -71,6 +74,7 @@ impl GlType for rust_t {
     const NUM_COMPONENTS: usize = n;
     const NORMALIZED: bool = false;
     const STRIDE: usize = std::mem::size_of::<Self>();
+    const COLUMNS: usize = m;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -82,6 +86,25 @@ impl<T: GlType> GlType for Normalized<T> {
     const NUM_COMPONENTS: usize = T::NUM_COMPONENTS;
     const NORMALIZED: bool = true;
     const STRIDE: usize = T::STRIDE;
+    const COLUMNS: usize = T::COLUMNS;
+}
+
+/// Matrix vertex attribute made of `N` columns of type `T`. Each column takes its own attribute
+/// location, so that a `mat4` attribute at location 1 also occupies locations 2 to 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct MatrixAttribute<const N: usize, T>(pub [T; N]);
+
+unsafe impl<const N: usize, T: bytemuck::Zeroable> bytemuck::Zeroable for MatrixAttribute<N, T> {}
+
+unsafe impl<const N: usize, T: bytemuck::Pod> bytemuck::Pod for MatrixAttribute<N, T> {}
+
+impl<const N: usize, T: GlType> GlType for MatrixAttribute<N, T> {
+    const GL_TYPE: GLenum = T::GL_TYPE;
+    const NUM_COMPONENTS: usize = T::NUM_COMPONENTS;
+    const NORMALIZED: bool = T::NORMALIZED;
+    const STRIDE: usize = N * T::STRIDE;
+    const COLUMNS: usize = N;
 }
 
 #[cfg(feature = "vertex-glam")]
@@ -123,4 +146,41 @@ impl GlType for rust_t {
     const NUM_COMPONENTS: usize = n;
     const NORMALIZED: bool = false;
     const STRIDE: usize = std::mem::size_of::<Self>();
+    const COLUMNS: usize = n;
+}
+
+#[cfg(feature = "vertex-nalgebra")]
+#[duplicate_item(
+n; [2]; [3]; [4];
+)]
+#[duplicate_item(
+rust_t                          gl_t;
+[nalgebra::SVector<f32, n>]     [gl::FLOAT];
+[nalgebra::SVector<f64, n>]     [gl::DOUBLE];
+[nalgebra::SVector<u32, n>]     [gl::UNSIGNED_INT];
+[nalgebra::SVector<i32, n>]     [gl::INT];
+)]
+impl GlType for rust_t {
+    const GL_TYPE: GLenum = gl_t;
+    const NUM_COMPONENTS: usize = n;
+    const NORMALIZED: bool = false;
+    const STRIDE: usize = std::mem::size_of::<Self>();
+}
+
+#[cfg(feature = "vertex-mint")]
+#[duplicate_item(
+rust_t                          n       columns;
+[mint::Vector2<f32>]            [2]     [1];
+[mint::Vector3<f32>]            [3]     [1];
+[mint::Vector4<f32>]            [4]     [1];
+[mint::ColumnMatrix2<f32>]      [2]     [2];
+[mint::ColumnMatrix3<f32>]      [3]     [3];
+[mint::ColumnMatrix4<f32>]      [4]     [4];
+)]
+impl GlType for rust_t {
+    const GL_TYPE: GLenum = gl::FLOAT;
+    const NUM_COMPONENTS: usize = n;
+    const NORMALIZED: bool = false;
+    const STRIDE: usize = std::mem::size_of::<Self>();
+    const COLUMNS: usize = columns;
 }
@@ -0,0 +1,70 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{
+    buffer::{Buffer, DynamicBuffer},
+    program::Program,
+    shader::Shader,
+    texture::{Sampler, SparseTexture, Texture},
+};
+
+/// Wrapper moving an OpenGL object across threads, bypassing the `!Send` marker that objects carry
+/// by default. Created with [`AssumeSend::assume_send`].
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct SendWrapper<T>(T);
+
+// Safety: upheld by the caller of `AssumeSend::assume_send`
+unsafe impl<T> Send for SendWrapper<T> {}
+
+impl<T> SendWrapper<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for SendWrapper<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for SendWrapper<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// Extension method on OpenGL objects to move them between threads, for applications managing
+/// several OpenGL contexts which share objects.
+pub trait AssumeSend: Sized {
+    /// Wrap the object so that it can be sent to another thread.
+    ///
+    /// # Safety
+    ///
+    /// The object must only be used, and dropped, on threads whose current context shares objects
+    /// with the context it was created in. The caller is responsible for synchronizing access
+    /// across contexts, i.e. with fences, and for not touching the object from two threads at once.
+    unsafe fn assume_send(self) -> SendWrapper<Self> {
+        SendWrapper(self)
+    }
+}
+
+// Only objects shared between contexts implement `AssumeSend`. Container objects (vertex arrays,
+// framebuffers, program pipelines) are never shared, and must stay on the context they were
+// created in.
+
+impl<T, const K: u32> AssumeSend for Buffer<T, K> {}
+
+impl<T> AssumeSend for DynamicBuffer<T> {}
+
+impl<F> AssumeSend for Texture<F> {}
+
+impl<F> AssumeSend for SparseTexture<F> {}
+
+impl AssumeSend for Sampler {}
+
+impl<S> AssumeSend for Program<S> {}
+
+impl<const K: u32> AssumeSend for Shader<K> {}
@@ -1,4 +1,5 @@
 use std::{
+    cell::RefCell,
     fmt::{self, Formatter},
     marker::PhantomData,
     num::{NonZeroU32, NonZeroUsize},
@@ -7,20 +8,25 @@ use std::{
 
 use bitflags::bitflags;
 use bytemuck::Pod;
+use crevice::std140::AsStd140;
 use eyre::Result;
-use gl::types::{GLbitfield, GLintptr, GLsizeiptr, GLuint};
+use gl::types::{GLbitfield, GLenum, GLintptr, GLsizeiptr, GLuint};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use once_cell::sync::Lazy;
 
 use crate::{
-    base::resource::{Resource, ResourceExt},
+    base::{
+        resource::{Resource, ResourceExt},
+        GlType,
+    },
     utils::gl_error_guard,
 };
 
 pub type ArrayBuffer<T> = Buffer<T, { gl::ARRAY_BUFFER }>;
 pub type ElementBuffer<T> = Buffer<T, { gl::ELEMENT_ARRAY_BUFFER }>;
 pub type UniformBuffer<T> = Buffer<T, { gl::UNIFORM_BUFFER }>;
+pub type IndirectBuffer<T> = Buffer<T, { gl::DRAW_INDIRECT_BUFFER }>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct BufferId<const K: u32> {
@@ -129,16 +135,19 @@ impl<'a, T: 'a, const K: u32> Resource<'a> for Buffer<T, K> {
     }
 
     fn bind(&self) {
-        tracing::trace!(
+        hot_log!(trace!(
             "glBindBuffer({:?}, {})",
             BufferKind::from_u32(K).unwrap(),
             self.id
-        );
+        ));
         unsafe { gl::BindBuffer(K, self.id.get() as _) };
     }
 
     fn unbind(&self) {
-        tracing::trace!("glBindBuffer({:?}, 0)", BufferKind::from_u32(K).unwrap());
+        hot_log!(trace!(
+            "glBindBuffer({:?}, 0)",
+            BufferKind::from_u32(K).unwrap()
+        ));
         unsafe { gl::BindBuffer(K, 0) };
     }
 }
@@ -152,7 +161,13 @@ impl<T, const K: u32> Buffer<T, K> {
         );
         let id = unsafe {
             let mut id = 0;
-            gl::GenBuffers(1, &mut id);
+            // DSA functions require the buffer object to exist, which `glGenBuffers` only does on
+            // first bind
+            if cfg!(feature = "dsa") && gl::CreateBuffers::is_loaded() {
+                gl::CreateBuffers(1, &mut id);
+            } else {
+                gl::GenBuffers(1, &mut id);
+            }
             id
         };
         tracing::debug!("Create buffer {}", id);
@@ -170,6 +185,11 @@ impl<T, const K: u32> Buffer<T, K> {
     pub fn len(&self) -> usize {
         self.count
     }
+
+    /// Label this buffer for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        crate::debug::label_object(gl::BUFFER, self.id.get(), label);
+    }
 }
 
 impl<T: Pod, const K: u32> Buffer<T, K> {
@@ -183,27 +203,69 @@ impl<T: Pod, const K: u32> Buffer<T, K> {
         Ok(this)
     }
 
+    /// Create a buffer of `len` elements with immutable storage (`glBufferStorage`), which
+    /// allows mapping it persistently with [`BufferSlice::map_persistent`]. The contents are
+    /// left uninitialized, and the storage cannot be reallocated with [`Self::set`] or
+    /// [`Self::resize`] afterwards. Requires OpenGL 4.4 or `GL_ARB_buffer_storage`.
+    pub fn with_storage(len: usize, flags: BufferAccess) -> Result<Self> {
+        eyre::ensure!(
+            gl::BufferStorage::is_loaded(),
+            "Immutable buffer storage requires OpenGL 4.4 or GL_ARB_buffer_storage"
+        );
+        let mut this = Self::new();
+        let size = len * Self::stride();
+        tracing::trace!(
+            "glBufferStorage({:?}, {}, NULL, {:?})",
+            BufferKind::from_u32(K).unwrap(),
+            size,
+            flags
+        );
+        gl_error_guard(|| {
+            this.with_binding(|| unsafe {
+                gl::BufferStorage(K, size as _, std::ptr::null(), flags.bits)
+            })
+        })?;
+        this.count = len;
+        Ok(this)
+    }
+
     /// Sets GPU data.
     pub fn set(&mut self, data: &[T], usage_hint: BufferUsageHint) -> Result<()> {
-        self.bind();
-        let bytes = if K == BufferKind::Uniform as u32 {
-            let sizeof = std::mem::size_of::<T>();
-            let gl_alignment = *GL_ALIGNMENT;
-            let alignment = next_multiple(sizeof, gl_alignment);
-            data.iter()
-                .flat_map(|x| {
-                    let bytes = bytemuck::bytes_of(x);
-                    let padding = alignment - bytes.len();
-                    bytes
-                        .iter()
-                        .copied()
-                        .chain(std::iter::repeat(0).take(padding))
-                })
-                .collect::<Vec<_>>()
-        } else {
-            bytemuck::cast_slice(data).to_owned()
-        };
         self.count = data.len();
+        Self::with_gpu_bytes(data, |bytes| self.upload(bytes, usage_hint))
+    }
+
+    /// Updates GPU data starting at the given element offset, without reallocating the buffer.
+    pub fn update(&mut self, offset: usize, data: &[T]) -> Result<()> {
+        eyre::ensure!(
+            offset + data.len() <= self.count,
+            "Buffer update exceeds buffer bounds"
+        );
+        if data.is_empty() {
+            return Ok(());
+        }
+        let byte_offset = offset * Self::stride();
+        Self::with_gpu_bytes(data, |bytes| self.upload_sub(byte_offset, bytes))
+    }
+
+    fn upload(&self, bytes: &[u8], usage_hint: BufferUsageHint) -> Result<()> {
+        if cfg!(feature = "dsa") && gl::NamedBufferData::is_loaded() {
+            tracing::trace!(
+                "glNamedBufferData({}, {}, <bytes ptr>, {:?})",
+                self.id,
+                bytes.len(),
+                usage_hint
+            );
+            return gl_error_guard(|| unsafe {
+                gl::NamedBufferData(
+                    self.id.get(),
+                    bytes.len() as _,
+                    bytes.as_ptr() as *const _,
+                    usage_hint as _,
+                );
+            });
+        }
+        self.bind();
         tracing::trace!(
             "glBufferData({:?}, {}, <bytes ptr>, {:?})",
             BufferKind::from_u32(K).unwrap(),
@@ -222,12 +284,293 @@ impl<T: Pod, const K: u32> Buffer<T, K> {
         Ok(())
     }
 
+    fn upload_sub(&self, byte_offset: usize, bytes: &[u8]) -> Result<()> {
+        if cfg!(feature = "dsa") && gl::NamedBufferSubData::is_loaded() {
+            tracing::trace!(
+                "glNamedBufferSubData({}, {}, {}, <bytes ptr>)",
+                self.id,
+                byte_offset,
+                bytes.len()
+            );
+            return gl_error_guard(|| unsafe {
+                gl::NamedBufferSubData(
+                    self.id.get(),
+                    byte_offset as _,
+                    bytes.len() as _,
+                    bytes.as_ptr() as *const _,
+                );
+            });
+        }
+        tracing::trace!(
+            "glBufferSubData({:?}, {}, {}, <bytes ptr>)",
+            BufferKind::from_u32(K).unwrap(),
+            byte_offset,
+            bytes.len()
+        );
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::BufferSubData(
+                    K,
+                    byte_offset as _,
+                    bytes.len() as _,
+                    bytes.as_ptr() as *const _,
+                );
+            })
+        })
+    }
+
+    /// Resize the buffer to hold `len` elements, keeping the existing contents which fit. The
+    /// buffer keeps its ID, so that vertex arrays referencing it stay valid.
+    pub fn resize(&mut self, len: usize, usage_hint: BufferUsageHint) -> Result<()> {
+        let stride = Self::stride();
+        let copy_size = self.count.min(len) * stride;
+        tracing::debug!("Resize buffer {} to {} elements", self.id, len);
+        gl_error_guard(|| unsafe {
+            let mut tmp = 0;
+            if copy_size > 0 {
+                gl::GenBuffers(1, &mut tmp);
+                gl::BindBuffer(gl::COPY_READ_BUFFER, self.id.get());
+                gl::BindBuffer(gl::COPY_WRITE_BUFFER, tmp);
+                gl::BufferData(
+                    gl::COPY_WRITE_BUFFER,
+                    copy_size as _,
+                    std::ptr::null(),
+                    gl::STREAM_COPY,
+                );
+                gl::CopyBufferSubData(
+                    gl::COPY_READ_BUFFER,
+                    gl::COPY_WRITE_BUFFER,
+                    0,
+                    0,
+                    copy_size as _,
+                );
+            }
+            gl::BindBuffer(gl::COPY_READ_BUFFER, tmp);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, self.id.get());
+            gl::BufferData(
+                gl::COPY_WRITE_BUFFER,
+                (len * stride) as _,
+                std::ptr::null(),
+                usage_hint as _,
+            );
+            if copy_size > 0 {
+                gl::CopyBufferSubData(
+                    gl::COPY_READ_BUFFER,
+                    gl::COPY_WRITE_BUFFER,
+                    0,
+                    0,
+                    copy_size as _,
+                );
+                gl::DeleteBuffers(1, &tmp);
+            }
+            gl::BindBuffer(gl::COPY_READ_BUFFER, 0);
+            gl::BindBuffer(gl::COPY_WRITE_BUFFER, 0);
+        })?;
+        self.count = len;
+        Ok(())
+    }
+
+    /// Invalidate the contents of the buffer, letting the driver orphan its storage instead of
+    /// synchronizing with pending draws on the next write.
+    pub fn invalidate(&mut self) -> Result<()> {
+        if gl::InvalidateBufferData::is_loaded() {
+            tracing::trace!("glInvalidateBufferData({})", self.id);
+            gl_error_guard(|| unsafe { gl::InvalidateBufferData(self.id.get()) })
+        } else {
+            // Orphan the buffer by re-specifying its storage
+            gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    let mut size = 0;
+                    let mut usage = 0;
+                    gl::GetBufferParameteriv(K, gl::BUFFER_SIZE, &mut size);
+                    gl::GetBufferParameteriv(K, gl::BUFFER_USAGE, &mut usage);
+                    gl::BufferData(K, size as _, std::ptr::null(), usage as _);
+                })
+            })
+        }
+    }
+
+    /// Run `f` with the bytes of the data as laid out in the GPU buffer. Elements of uniform
+    /// buffers are padded to the uniform buffer offset alignment, in a per-thread scratch buffer
+    /// which is reused across uploads. The scratch buffer shrinks back to
+    /// [`MAX_SCRATCH_CAPACITY`] after larger uploads.
+    fn with_gpu_bytes<R>(data: &[T], f: impl FnOnce(&[u8]) -> R) -> R {
+        if K != BufferKind::Uniform as u32 {
+            return f(bytemuck::cast_slice(data));
+        }
+        thread_local! {
+            static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+        }
+        let alignment = Self::stride();
+        SCRATCH.with(|scratch| {
+            let mut scratch = scratch.borrow_mut();
+            scratch.clear();
+            scratch.resize(data.len() * alignment, 0);
+            for (chunk, x) in scratch.chunks_exact_mut(alignment).zip(data) {
+                let bytes = bytemuck::bytes_of(x);
+                chunk[..bytes.len()].copy_from_slice(bytes);
+            }
+            let ret = f(&scratch);
+            if scratch.capacity() > MAX_SCRATCH_CAPACITY {
+                scratch.clear();
+                scratch.shrink_to(MAX_SCRATCH_CAPACITY);
+            }
+            ret
+        })
+    }
+
+    /// Fill the whole buffer with the given value.
+    pub fn clear(&mut self, value: &T) -> Result<()>
+    where
+        T: GlType,
+    {
+        let (internal_format, format) = Self::clear_format()?;
+        if !gl::ClearBufferData::is_loaded() {
+            return self.fill_range(0..self.count * Self::stride(), value);
+        }
+        tracing::trace!(
+            "glClearBufferData({:?}, {:x}, {:x}, {:x}, <value ptr>)",
+            BufferKind::from_u32(K).unwrap(),
+            internal_format,
+            format,
+            T::GL_TYPE
+        );
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferData(
+                    K,
+                    internal_format,
+                    format,
+                    T::GL_TYPE,
+                    bytemuck::bytes_of(value).as_ptr().cast(),
+                )
+            })
+        })
+    }
+
+    /// Fill the given range of elements of the buffer with the given value.
+    pub fn clear_range(&mut self, range: impl RangeBounds<usize>, value: &T) -> Result<()>
+    where
+        T: GlType,
+    {
+        let (internal_format, format) = Self::clear_format()?;
+        let (_, range) = self.byte_slice(range);
+        eyre::ensure!(range.start <= range.end, "Clear range starts after its end");
+        eyre::ensure!(
+            range.end <= self.count * Self::stride(),
+            "Clear range exceeds buffer bounds"
+        );
+        if !gl::ClearBufferSubData::is_loaded() {
+            return self.fill_range(range, value);
+        }
+        tracing::trace!(
+            "glClearBufferSubData({:?}, {:x}, {}, {}, {:x}, {:x}, <value ptr>)",
+            BufferKind::from_u32(K).unwrap(),
+            internal_format,
+            range.start,
+            range.end - range.start,
+            format,
+            T::GL_TYPE
+        );
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferSubData(
+                    K,
+                    internal_format,
+                    range.start as _,
+                    (range.end - range.start) as _,
+                    format,
+                    T::GL_TYPE,
+                    bytemuck::bytes_of(value).as_ptr().cast(),
+                )
+            })
+        })
+    }
+
+    /// Fallback of the buffer clears when `glClearBufferData` (OpenGL 4.3) isn't available,
+    /// uploading the value repeated over the byte range instead.
+    fn fill_range(&self, range: Range<usize>, value: &T) -> Result<()> {
+        let bytes = bytemuck::bytes_of(value);
+        let data = bytes.repeat((range.end - range.start) / bytes.len());
+        tracing::trace!(
+            "glBufferSubData({:?}, {}, {}, <data ptr>)",
+            BufferKind::from_u32(K).unwrap(),
+            range.start,
+            data.len()
+        );
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::BufferSubData(K, range.start as _, data.len() as _, data.as_ptr().cast())
+            })
+        })
+    }
+
+    /// Sized internal format and pixel format used to clear the buffer with values of type `T`.
+    fn clear_format() -> Result<(GLenum, GLenum)>
+    where
+        T: GlType,
+    {
+        eyre::ensure!(
+            K != BufferKind::Uniform as u32,
+            "Cannot clear uniform buffers as their elements are padded"
+        );
+        let component_size = match T::GL_TYPE {
+            gl::FLOAT | gl::UNSIGNED_INT | gl::INT => 4,
+            gl::UNSIGNED_SHORT | gl::SHORT => 2,
+            gl::UNSIGNED_BYTE | gl::BYTE => 1,
+            _ => eyre::bail!("Unsupported component type for buffer clears"),
+        };
+        eyre::ensure!(
+            component_size * T::NUM_COMPONENTS == std::mem::size_of::<T>(),
+            "Only scalar and vector types can be used to clear buffers"
+        );
+        let format = match (T::GL_TYPE == gl::FLOAT, T::NUM_COMPONENTS) {
+            (true, 1) => gl::RED,
+            (true, 2) => gl::RG,
+            (true, 3) => gl::RGB,
+            (true, 4) => gl::RGBA,
+            (false, 1) => gl::RED_INTEGER,
+            (false, 2) => gl::RG_INTEGER,
+            (false, 3) => gl::RGB_INTEGER,
+            (false, 4) => gl::RGBA_INTEGER,
+            _ => eyre::bail!("Unsupported component count for buffer clears"),
+        };
+        let internal_format = match (T::GL_TYPE, T::NUM_COMPONENTS) {
+            (gl::FLOAT, 1) => gl::R32F,
+            (gl::FLOAT, 2) => gl::RG32F,
+            (gl::FLOAT, 3) => gl::RGB32F,
+            (gl::FLOAT, 4) => gl::RGBA32F,
+            (gl::UNSIGNED_INT, 1) => gl::R32UI,
+            (gl::UNSIGNED_INT, 2) => gl::RG32UI,
+            (gl::UNSIGNED_INT, 3) => gl::RGB32UI,
+            (gl::UNSIGNED_INT, 4) => gl::RGBA32UI,
+            (gl::INT, 1) => gl::R32I,
+            (gl::INT, 2) => gl::RG32I,
+            (gl::INT, 3) => gl::RGB32I,
+            (gl::INT, 4) => gl::RGBA32I,
+            (gl::UNSIGNED_SHORT, 1) => gl::R16UI,
+            (gl::UNSIGNED_SHORT, 2) => gl::RG16UI,
+            (gl::UNSIGNED_SHORT, 4) => gl::RGBA16UI,
+            (gl::SHORT, 1) => gl::R16I,
+            (gl::SHORT, 2) => gl::RG16I,
+            (gl::SHORT, 4) => gl::RGBA16I,
+            (gl::UNSIGNED_BYTE, 1) => gl::R8UI,
+            (gl::UNSIGNED_BYTE, 2) => gl::RG8UI,
+            (gl::UNSIGNED_BYTE, 4) => gl::RGBA8UI,
+            (gl::BYTE, 1) => gl::R8I,
+            (gl::BYTE, 2) => gl::RG8I,
+            (gl::BYTE, 4) => gl::RGBA8I,
+            _ => eyre::bail!("No internal format available to clear buffers of this type"),
+        };
+        Ok((internal_format, format))
+    }
+
     pub fn at(&self, ix: usize) -> BufferSlice<T, K> {
         self.slice(ix..=ix)
     }
 
     pub fn slice(&self, range: impl RangeBounds<usize>) -> BufferSlice<T, K> {
-        let (alignment, range) = self.byte_slice(std::mem::size_of::<T>(), range);
+        let (alignment, range) = self.byte_slice(range);
         let offset = range.start as _;
         let size = (range.end - range.start) as _;
         BufferSlice {
@@ -238,9 +581,20 @@ impl<T: Pod, const K: u32> Buffer<T, K> {
         }
     }
 
-    fn byte_slice(&self, sizeof: usize, range: impl RangeBounds<usize>) -> (usize, Range<usize>) {
+    /// Size in bytes of one element in the GPU buffer. Uniform buffers pad each element to the
+    /// uniform buffer offset alignment, other buffer kinds are tightly packed.
+    fn stride() -> usize {
+        let sizeof = std::mem::size_of::<T>();
+        if K == BufferKind::Uniform as u32 {
+            next_multiple(sizeof, *GL_ALIGNMENT)
+        } else {
+            sizeof
+        }
+    }
+
+    fn byte_slice(&self, range: impl RangeBounds<usize>) -> (usize, Range<usize>) {
         tracing::trace!(range.start = ?range.start_bound(), range.end = ?range.end_bound());
-        let alignment = next_multiple(sizeof, *GL_ALIGNMENT);
+        let alignment = Self::stride();
         let start = match range.start_bound() {
             Bound::Included(i) => *i,
             Bound::Excluded(i) => i + 1,
@@ -249,18 +603,108 @@ impl<T: Pod, const K: u32> Buffer<T, K> {
         let end = match range.end_bound() {
             Bound::Included(i) => i + 1,
             Bound::Excluded(i) => *i,
-            Bound::Unbounded => self.count * std::mem::size_of::<T>(),
+            Bound::Unbounded => self.count,
         } * alignment;
         (alignment, start..end)
     }
 }
 
+impl<T: AsStd140> UniformBuffer<T> {
+    /// Check that the Rust layout of `T` has the same size as its std140 layout, which is what
+    /// shaders expect uniform blocks to be laid out as. Note that this cannot catch fields being
+    /// reordered; deriving `AsStd140` and uploading the std140 version is the reliable way of
+    /// getting a correct layout.
+    pub fn validate_std140() -> Result<()> {
+        let size = std::mem::size_of::<T>();
+        let expected = std140_size::<T>();
+        eyre::ensure!(
+            size == expected,
+            "Type {} is {} bytes long but its std140 layout is {} bytes long, check its padding",
+            std::any::type_name::<T>(),
+            size,
+            expected
+        );
+        Ok(())
+    }
+}
+
+/// Size in bytes of `T` when laid out following the std140 rules.
+pub fn std140_size<T: AsStd140>() -> usize {
+    T::std140_size_static()
+}
+
+#[derive(Debug)]
+/// Growable vertex buffer, for geometry which is rebuilt every frame such as immediate-mode UIs.
+/// GPU storage grows geometrically as data is appended, and is orphaned when cleared.
+pub struct DynamicBuffer<T> {
+    buffer: ArrayBuffer<T>,
+    len: usize,
+}
+
+#[allow(clippy::new_without_default)]
+impl<T: Pod> DynamicBuffer<T> {
+    pub fn new() -> Self {
+        Self {
+            buffer: ArrayBuffer::new(),
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Result<Self> {
+        let mut this = Self::new();
+        this.buffer.resize(capacity, BufferUsageHint::Dynamic)?;
+        Ok(this)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Number of elements the buffer can hold before having to grow.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Underlying array buffer, to bind into vertex arrays. Only the first [`len`](Self::len)
+    /// elements are valid.
+    pub fn buffer(&self) -> &ArrayBuffer<T> {
+        &self.buffer
+    }
+
+    /// Remove all elements, orphaning the GPU storage so that it can be refilled without waiting
+    /// on draws still using the previous contents.
+    pub fn clear(&mut self) -> Result<()> {
+        self.len = 0;
+        if self.capacity() > 0 {
+            self.buffer.invalidate()?;
+        }
+        Ok(())
+    }
+
+    /// Append data at the end of the buffer, growing it if needed.
+    pub fn extend(&mut self, data: &[T]) -> Result<()> {
+        let needed = self.len + data.len();
+        if needed > self.capacity() {
+            let capacity = needed.max(2 * self.capacity());
+            self.buffer.resize(capacity, BufferUsageHint::Dynamic)?;
+        }
+        self.buffer.update(self.len, data)?;
+        self.len = needed;
+        Ok(())
+    }
+}
+
 bitflags! {
     pub struct BufferAccess: GLbitfield {
         const PERSISTENT = gl::MAP_PERSISTENT_BIT;
         const COHERENT = gl::MAP_COHERENT_BIT;
         const MAP_READ = gl::MAP_READ_BIT;
         const MAP_WRITE = gl::MAP_WRITE_BIT;
+        const FLUSH_EXPLICIT = gl::MAP_FLUSH_EXPLICIT_BIT;
     }
 }
 
@@ -323,6 +767,116 @@ impl<'buf, T: bytemuck::Pod, const K: u32> BufferSlice<'buf, T, K> {
             })
         })
     }
+
+    /// Persistently map this slice for writing, for buffers created with
+    /// [`Buffer::with_storage`] and the [`BufferAccess::PERSISTENT`] and
+    /// [`BufferAccess::MAP_WRITE`] flags. The mapping stays valid while the buffer is used by
+    /// OpenGL; unless [`BufferAccess::COHERENT`] is given, writes only become visible to OpenGL
+    /// once flushed with [`PersistentMapping::flush_range`].
+    pub fn map_persistent(&self, access: BufferAccess) -> Result<PersistentMapping<'buf, T, K>> {
+        eyre::ensure!(
+            access.contains(BufferAccess::PERSISTENT | BufferAccess::MAP_WRITE),
+            "Persistent mappings need the PERSISTENT and MAP_WRITE access flags"
+        );
+        let explicit_flush = !access.contains(BufferAccess::COHERENT);
+        let access = if explicit_flush {
+            access | BufferAccess::FLUSH_EXPLICIT
+        } else {
+            access
+        };
+        let ptr = gl_error_guard(|| {
+            self.buffer.with_binding(|| unsafe {
+                gl::MapBufferRange(K, self.offset, self.size, access.bits)
+            })
+        })?;
+        eyre::ensure!(!ptr.is_null(), "Cannot map buffer {}", self.buffer.id);
+        tracing::debug!(
+            "Persistently map buffer {} ({}..{})",
+            self.buffer.id,
+            self.offset,
+            self.offset + self.size
+        );
+        Ok(PersistentMapping {
+            buffer: self.buffer,
+            ptr: ptr as *mut u8,
+            len: self.size as usize / self.alignment,
+            alignment: self.alignment,
+            explicit_flush,
+        })
+    }
+}
+
+/// Persistent write mapping of a buffer slice, created by [`BufferSlice::map_persistent`]. The
+/// buffer is unmapped when this is dropped.
+#[derive(Debug)]
+pub struct PersistentMapping<'buf, T, const K: u32> {
+    buffer: &'buf Buffer<T, K>,
+    ptr: *mut u8,
+    len: usize,
+    alignment: usize,
+    explicit_flush: bool,
+}
+
+impl<'buf, T: bytemuck::Pod, const K: u32> PersistentMapping<'buf, T, K> {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements in the mapping.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Write elements into the mapping, starting at the given element index of the mapping.
+    pub fn write(&mut self, at: usize, data: &[T]) -> Result<()> {
+        eyre::ensure!(
+            at + data.len() <= self.len,
+            "Write exceeds the mapping bounds"
+        );
+        for (i, value) in data.iter().enumerate() {
+            let bytes = bytemuck::bytes_of(value);
+            unsafe {
+                let dst = self.ptr.add((at + i) * self.alignment);
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), dst, bytes.len());
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the given range of elements of the mapping, making writes to them visible to
+    /// OpenGL. Coherent mappings don't need to be flushed, and this does nothing for them.
+    pub fn flush_range(&self, range: Range<usize>) -> Result<()> {
+        eyre::ensure!(
+            range.start <= range.end && range.end <= self.len,
+            "Flush range exceeds the mapping bounds"
+        );
+        if !self.explicit_flush {
+            return Ok(());
+        }
+        let offset = (range.start * self.alignment) as GLintptr;
+        let length = ((range.end - range.start) * self.alignment) as GLsizeiptr;
+        tracing::trace!(
+            "glFlushMappedBufferRange({:?}, {}, {})",
+            BufferKind::from_u32(K).unwrap(),
+            offset,
+            length
+        );
+        gl_error_guard(|| {
+            self.buffer
+                .with_binding(|| unsafe { gl::FlushMappedBufferRange(K, offset, length) })
+        })
+    }
+}
+
+impl<'buf, T, const K: u32> Drop for PersistentMapping<'buf, T, K> {
+    fn drop(&mut self) {
+        tracing::debug!("Unmap buffer {}", self.buffer.id);
+        unsafe {
+            gl::BindBuffer(K, self.buffer.id.get());
+            gl::UnmapBuffer(K);
+            gl::BindBuffer(K, 0);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -345,12 +899,17 @@ impl<'m, 'b, T, const K: u32> Drop for MappedBufferData<'m, 'b, T, K> {
     fn drop(&mut self) {
         tracing::debug!("Unmap buffer {}", self.id);
         unsafe {
+            // The buffer was unbound once mapped, and something else may be bound by now
+            gl::BindBuffer(K, self.id.get());
             gl::UnmapBuffer(K);
             gl::BindBuffer(K, 0);
         }
     }
 }
 
+/// Capacity, in bytes, the scratch buffer used to pad uniform buffer uploads keeps between uploads.
+const MAX_SCRATCH_CAPACITY: usize = 64 * 1024;
+
 #[cfg(not(feature = "fast"))]
 static GL_ALIGNMENT: Lazy<NonZeroUsize> = Lazy::new(|| {
     NonZeroUsize::new(
@@ -380,8 +939,45 @@ static GL_ALIGNMENT: Lazy<NonZeroUsize> = Lazy::new(|| unsafe {
 });
 
 #[inline(always)]
+/// Rounds `x` up to the nearest multiple of `of`. Values already aligned are returned as-is.
 fn next_multiple(x: usize, of: NonZeroUsize) -> usize {
-    let rem = x % of.get();
-    let offset = of.get() - rem;
-    x + offset
+    match x % of.get() {
+        0 => x,
+        rem => x + of.get() - rem,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn align(of: usize) -> NonZeroUsize {
+        NonZeroUsize::new(of).unwrap()
+    }
+
+    #[test]
+    fn next_multiple_keeps_aligned_values() {
+        assert_eq!(next_multiple(256, align(256)), 256);
+        assert_eq!(next_multiple(512, align(256)), 512);
+        assert_eq!(next_multiple(7, align(1)), 7);
+    }
+
+    #[test]
+    fn next_multiple_rounds_up_unaligned_values() {
+        assert_eq!(next_multiple(1, align(256)), 256);
+        assert_eq!(next_multiple(255, align(256)), 256);
+        assert_eq!(next_multiple(257, align(256)), 512);
+    }
+
+    #[test]
+    fn next_multiple_of_zero_is_zero() {
+        assert_eq!(next_multiple(0, align(256)), 0);
+    }
+
+    #[test]
+    fn std140_size_of_scalars() {
+        assert_eq!(std140_size::<f32>(), 4);
+        assert_eq!(std140_size::<u32>(), 4);
+        assert!(UniformBuffer::<f32>::validate_std140().is_ok());
+    }
 }
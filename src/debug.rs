@@ -1,8 +1,16 @@
-use std::{cell::RefCell, ffi::c_void};
+use std::{
+    ffi::c_void,
+    marker::PhantomData,
+    sync::{Mutex, PoisonError},
+};
 
+use eyre::Result;
 use gl::types::{GLchar, GLenum, GLsizei, GLuint};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use once_cell::sync::Lazy;
+
+use crate::utils::GlError;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[repr(u32)]
@@ -47,9 +55,11 @@ pub struct GlDebugData {
     pub severity: CallbackSeverity,
 }
 
-type UserCallback = Box<dyn Fn(GlDebugData)>;
+type UserCallback = Box<dyn Fn(GlDebugData) + Send + Sync>;
 
-static mut USER_CALLBACK: RefCell<Option<UserCallback>> = RefCell::new(None);
+/// The driver may call the debug callback from another thread when debug output is not
+/// synchronous, hence the lock.
+static USER_CALLBACK: Lazy<Mutex<Option<UserCallback>>> = Lazy::new(|| Mutex::new(None));
 
 extern "system" fn message_callback(
     source: GLenum,
@@ -60,7 +70,10 @@ extern "system" fn message_callback(
     message: *const GLchar,
     _user_param: *mut c_void,
 ) {
-    if let Some(user_callback) = unsafe { USER_CALLBACK.get_mut().as_mut() } {
+    let user_callback = USER_CALLBACK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(user_callback) = user_callback.as_ref() {
         let data = GlDebugData {
             source: CallbackSource::from_u32(source).unwrap(),
             r#type: CallbackType::from_u32(r#type).unwrap(),
@@ -77,18 +90,88 @@ extern "system" fn message_callback(
     }
 }
 
-pub fn set_message_callback<F: 'static + Fn(GlDebugData)>(cb: F) {
+/// Set the callback receiving OpenGL debug messages, replacing any previous one. The callback must
+/// not make OpenGL calls itself, as messages they generate would deadlock on re-entry.
+pub fn set_message_callback<F: 'static + Fn(GlDebugData) + Send + Sync>(cb: F) {
     if !gl::DebugMessageCallback::is_loaded() {
         tracing::warn!("glDebugMessageCallback is not available, cannot set debug callback");
     } else {
+        USER_CALLBACK
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .replace(Box::new(cb));
         unsafe {
-            USER_CALLBACK.get_mut().replace(Box::new(cb));
             gl::Enable(gl::DEBUG_OUTPUT);
             gl::DebugMessageCallback(Some(message_callback), std::ptr::null_mut());
         }
     }
 }
 
+/// Remove the debug message callback, dropping the stored closure.
+pub fn clear_message_callback() {
+    if gl::DebugMessageCallback::is_loaded() {
+        unsafe {
+            gl::DebugMessageCallback(None, std::ptr::null());
+        }
+    }
+    USER_CALLBACK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .take();
+}
+
+/// Enable or disable debug output altogether.
+pub fn enable(enabled: bool) {
+    unsafe {
+        if enabled {
+            gl::Enable(gl::DEBUG_OUTPUT);
+        } else {
+            gl::Disable(gl::DEBUG_OUTPUT);
+        }
+    }
+}
+
+/// Make the driver call the debug callback synchronously, from within the OpenGL call that
+/// generated the message. This makes stack traces taken in the callback meaningful, at a
+/// performance cost.
+pub fn set_synchronous(synchronous: bool) {
+    unsafe {
+        if synchronous {
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        } else {
+            gl::Disable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+    }
+}
+
+/// Enable or disable debug messages matching the given source, type and severity. `None` matches
+/// all values.
+pub fn set_message_control(
+    source: Option<CallbackSource>,
+    type_: Option<CallbackType>,
+    severity: Option<CallbackSeverity>,
+    enabled: bool,
+) {
+    if !gl::DebugMessageControl::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::DebugMessageControl(
+            source.map_or(gl::DONT_CARE, |s| s as _),
+            type_.map_or(gl::DONT_CARE, |t| t as _),
+            severity.map_or(gl::DONT_CARE, |s| s as _),
+            0,
+            std::ptr::null(),
+            if enabled { gl::TRUE } else { gl::FALSE },
+        );
+    }
+}
+
+/// Disable notification messages, which most drivers emit in large quantities.
+pub fn mute_notifications() {
+    set_message_control(None, None, Some(CallbackSeverity::Notification), false);
+}
+
 pub fn hook_gl_to_tracing() {
     use CallbackSeverity::*;
     set_message_callback(|data| {
@@ -108,3 +191,97 @@ pub fn hook_gl_to_tracing() {
         };
     });
 }
+
+/// Open a named debug group, showing up in graphics debuggers such as RenderDoc. Does nothing when
+/// debug groups are not supported by the driver.
+pub fn push_group(message: &str) {
+    if !gl::PushDebugGroup::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::PushDebugGroup(
+            gl::DEBUG_SOURCE_APPLICATION,
+            0,
+            message.len() as _,
+            message.as_ptr() as *const _,
+        );
+    }
+}
+
+/// Close the last debug group opened with [`push_group`].
+pub fn pop_group() {
+    if !gl::PopDebugGroup::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::PopDebugGroup();
+    }
+}
+
+/// Debug group guard, closing the group when dropped.
+#[derive(Debug)]
+pub struct DebugGroup {
+    __non_send: PhantomData<*mut ()>,
+}
+
+impl DebugGroup {
+    pub fn new(message: &str) -> Self {
+        push_group(message);
+        Self {
+            __non_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        pop_group();
+    }
+}
+
+/// Attach a human-readable label to an OpenGL object, shown in graphics debuggers. The
+/// identifier is the namespace of the object (i.e. `GL_TEXTURE`). Does nothing when object labels
+/// are not supported by the driver.
+pub fn label_object(identifier: GLenum, name: u32, label: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            name,
+            label.len() as _,
+            label.as_ptr() as *const _,
+        );
+    }
+}
+
+/// Drain all pending OpenGL errors, returning them in a single error labeled with the checkpoint
+/// name. Useful to find out which phase of a long sequence of calls left errors behind.
+pub fn assert_no_error(checkpoint: &str) -> Result<()> {
+    let mut errors = vec![];
+    loop {
+        let error = unsafe { gl::GetError() };
+        if error == gl::NO_ERROR {
+            break;
+        }
+        errors.push(
+            GlError::from_u32(error)
+                .map(|err| err.to_string())
+                .unwrap_or_else(|| format!("Unknown OpenGL error {:#x}", error)),
+        );
+        // A lost context can keep reporting errors forever
+        if error == gl::CONTEXT_LOST {
+            break;
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        eyre::bail!(
+            "OpenGL errors at checkpoint {:?}: {}",
+            checkpoint,
+            errors.join(", ")
+        )
+    }
+}
@@ -1,22 +1,33 @@
 use std::marker::PhantomData;
 use std::{
     fmt::{self, Formatter},
+    num::NonZeroU32,
     ops::{Range, RangeBounds},
 };
 
 use bitflags::bitflags;
+use bytemuck::Zeroable;
 use eyre::Result;
 use gl::types::*;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use once_cell::sync::Lazy;
 
 use crate::texture::Mipmap;
 use crate::{
-    base::resource::{Resource, ResourceExt},
-    program::Program,
-    texture::{DepthStencil, Dimension, Texture},
-    utils::{gl_error_guard, GlRef},
-    vertex::{DrawMode, VertexArray},
+    base::{
+        resource::{Resource, ResourceExt},
+        GlType,
+    },
+    buffer::IndirectBuffer,
+    program::ShaderPipeline,
+    query,
+    texture::{DepthFormat, DepthStencil, Dimension, SampleMode, Texture, TextureFormat},
+    utils::{gl_error_guard, row_alignment, GlRef},
+    vertex::{
+        fullscreen_vao, index_size, DrawArraysIndirectCommand, DrawElementsIndirectCommand,
+        DrawMode, VertexArray,
+    },
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -59,7 +70,7 @@ bitflags! {
     }
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromPrimitive)]
 #[repr(u32)]
 pub enum Blend {
     Zero = gl::ZERO,
@@ -83,9 +94,10 @@ pub enum Blend {
     OneMinusSrc1Alpha = gl::ONE_MINUS_SRC1_ALPHA,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromPrimitive)]
 #[repr(u32)]
 pub enum DepthTestFunction {
+    Never = gl::NEVER,
     Less = gl::LESS,
     Equal = gl::EQUAL,
     LEqual = gl::LEQUAL,
@@ -95,6 +107,51 @@ pub enum DepthTestFunction {
     Always = gl::ALWAYS,
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromPrimitive)]
+#[repr(u32)]
+pub enum StencilOp {
+    Keep = gl::KEEP,
+    Zero = gl::ZERO,
+    Replace = gl::REPLACE,
+    Incr = gl::INCR,
+    IncrWrap = gl::INCR_WRAP,
+    Decr = gl::DECR,
+    DecrWrap = gl::DECR_WRAP,
+    Invert = gl::INVERT,
+}
+
+/// Attachment point of a framebuffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramebufferAttachment {
+    /// Color attachment at the given index. On the backbuffer, this refers to the back buffer.
+    Color(u32),
+    Depth,
+    Stencil,
+    DepthStencil,
+}
+
+impl FramebufferAttachment {
+    fn gl_attachment(&self, backbuffer: bool) -> GLenum {
+        use FramebufferAttachment::*;
+        match (self, backbuffer) {
+            (Color(_), true) => gl::BACK_LEFT,
+            (Color(ix), false) => gl::COLOR_ATTACHMENT0 + ix,
+            (Depth | DepthStencil, true) => gl::DEPTH,
+            (Depth, false) => gl::DEPTH_ATTACHMENT,
+            (Stencil, true) => gl::STENCIL,
+            (Stencil, false) => gl::STENCIL_ATTACHMENT,
+            (DepthStencil, false) => gl::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[repr(u32)]
+pub enum ColorEncoding {
+    Linear = gl::LINEAR,
+    Srgb = gl::SRGB,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[repr(u32)]
 pub enum FramebufferStatus {
@@ -119,6 +176,72 @@ pub enum BlendFunction {
     Max = gl::MAX,
 }
 
+/// Blending state of the OpenGL context.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct BlendState {
+    pub enabled: bool,
+    pub source_rgb: Blend,
+    pub target_rgb: Blend,
+    pub source_alpha: Blend,
+    pub target_alpha: Blend,
+    pub equation_rgb: BlendFunction,
+    pub equation_alpha: BlendFunction,
+    pub color: [f32; 4],
+}
+
+impl BlendState {
+    /// Query the current blending state. Errors if the driver reports a blend factor or equation
+    /// this crate does not know about.
+    pub fn current() -> Result<Self> {
+        let blend = |pname| {
+            let value = query::get_i32(pname);
+            Blend::from_i32(value).ok_or_else(|| eyre::eyre!("Unknown blend factor {:#x}", value))
+        };
+        let equation = |pname| {
+            let value = query::get_i32(pname);
+            BlendFunction::from_i32(value)
+                .ok_or_else(|| eyre::eyre!("Unknown blend equation {:#x}", value))
+        };
+        let color = query::get_f32v(gl::BLEND_COLOR);
+        let enabled = unsafe { gl::IsEnabled(gl::BLEND) == gl::TRUE };
+        Ok(Self {
+            enabled,
+            source_rgb: blend(gl::BLEND_SRC_RGB)?,
+            target_rgb: blend(gl::BLEND_DST_RGB)?,
+            source_alpha: blend(gl::BLEND_SRC_ALPHA)?,
+            target_alpha: blend(gl::BLEND_DST_ALPHA)?,
+            equation_rgb: equation(gl::BLEND_EQUATION_RGB)?,
+            equation_alpha: equation(gl::BLEND_EQUATION_ALPHA)?,
+            color,
+        })
+    }
+
+    /// Apply this blending state to the OpenGL context.
+    pub fn apply(&self) {
+        let [red, green, blue, alpha] = self.color;
+        unsafe {
+            gl::BlendFuncSeparate(
+                self.source_rgb as _,
+                self.target_rgb as _,
+                self.source_alpha as _,
+                self.target_alpha as _,
+            );
+            gl::BlendEquationSeparate(self.equation_rgb as _, self.equation_alpha as _);
+            gl::BlendColor(red, green, blue, alpha);
+            if self.enabled {
+                gl::Enable(gl::BLEND);
+            } else {
+                gl::Disable(gl::BLEND);
+            }
+        }
+    }
+}
+
+/// OpenGL framebuffer object.
+///
+/// Attachments only borrow textures for the duration of the call, as the framebuffer does not own
+/// them: the same texture can be attached to several framebuffers at once, for example to share a
+/// depth buffer between render passes. The texture must outlive its use in these framebuffers.
 #[derive(Debug)]
 pub struct Framebuffer {
     __non_send: PhantomData<*mut ()>,
@@ -131,6 +254,12 @@ impl Framebuffer {
             gl::BlendEquation(func as _);
         }
     }
+
+    pub fn blend_equation_separate(rgb: BlendFunction, alpha: BlendFunction) {
+        unsafe {
+            gl::BlendEquationSeparate(rgb as _, alpha as _);
+        }
+    }
 }
 
 impl std::ops::Deref for Framebuffer {
@@ -167,6 +296,23 @@ impl Framebuffer {
             id: FramebufferId::new(id).unwrap(),
         }
     }
+
+    /// Start building a new framebuffer. See [`FramebufferBuilder`].
+    pub fn builder() -> FramebufferBuilder {
+        FramebufferBuilder::new()
+    }
+
+    /// Maximum number of color attachments of a framebuffer (`GL_MAX_COLOR_ATTACHMENTS`). The
+    /// value is queried once and cached.
+    pub fn max_color_attachments() -> u32 {
+        *MAX_COLOR_ATTACHMENTS
+    }
+
+    /// Maximum number of draw buffers which can be enabled at once (`GL_MAX_DRAW_BUFFERS`). The
+    /// value is queried once and cached.
+    pub fn max_draw_buffers() -> u32 {
+        *MAX_DRAW_BUFFERS
+    }
 }
 
 impl<'a> Resource<'a> for Framebuffer {
@@ -185,7 +331,7 @@ impl<'a> Resource<'a> for Framebuffer {
     }
 
     fn bind(&self) {
-        tracing::trace!("Bind framebuffer {}", self.id);
+        hot_log!(trace!("Bind framebuffer {}", self.id));
         unsafe {
             gl::BindFramebuffer(gl::FRAMEBUFFER, self.id.0 as _);
         }
@@ -195,18 +341,14 @@ impl<'a> Resource<'a> for Framebuffer {
         if self.id == FramebufferId::BACKBUFFER {
             return;
         }
-        tracing::trace!("Unbind framebuffer {}", self.id);
+        hot_log!(trace!("Unbind framebuffer {}", self.id));
         unsafe { gl::BindFramebuffer(gl::FRAMEBUFFER, 0) }
     }
 }
 
 impl Framebuffer {
     pub fn get_viewport() -> [i32; 4] {
-        let mut viewport = [0; 4];
-        unsafe {
-            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
-        }
-        viewport
+        query::get_i32v(gl::VIEWPORT)
     }
     pub fn viewport(x: i32, y: i32, width: i32, height: i32) {
         unsafe {
@@ -214,6 +356,7 @@ impl Framebuffer {
         }
     }
 
+    /// Set the clear color used by [`Self::do_clear`].
     pub fn clear_color([red, green, blue, alpha]: [f32; 4]) {
         unsafe { gl::ClearColor(red, green, blue, alpha) }
     }
@@ -224,12 +367,104 @@ impl Framebuffer {
         }
     }
 
-    pub fn do_clear(&self, mode: ClearBuffer) {
-        self.with_binding(|| unsafe {
-            gl::Clear(mode.bits());
+    pub fn clear_stencil(value: i32) {
+        unsafe {
+            gl::ClearStencil(value);
+        }
+    }
+
+    /// Clear the given buffers of this framebuffer to the values set with [`Self::clear_color`],
+    /// [`Self::clear_depth`] and [`Self::clear_stencil`]. Float clear values are undefined for
+    /// integer color attachments, so clearing the color errors if this framebuffer draws to any;
+    /// use [`Self::clear_buffer_i`] or [`Self::clear_buffer_u`] to clear those instead.
+    pub fn do_clear(&self, mode: ClearBuffer) -> Result<()> {
+        self.with_binding(|| {
+            if mode.contains(ClearBuffer::COLOR) {
+                let integer_buffer = (0..Self::max_draw_buffers()).find(|&i| {
+                    matches!(
+                        draw_buffer_component_type(i),
+                        Some(gl::INT | gl::UNSIGNED_INT)
+                    )
+                });
+                if let Some(i) = integer_buffer {
+                    eyre::bail!(
+                        "Draw buffer {} has an integer format and cannot be cleared with a float \
+                        color, use clear_buffer_i or clear_buffer_u instead",
+                        i
+                    );
+                }
+            }
+            gl_error_guard(|| unsafe { gl::Clear(mode.bits()) })
+        })
+    }
+
+    /// Clear a single color draw buffer of this framebuffer to the given value.
+    pub fn clear_color_buffer(&self, draw_buffer: u32, value: [f32; 4]) -> Result<()> {
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferfv(gl::COLOR, draw_buffer as _, value.as_ptr());
+            })
+        })
+    }
+
+    /// Clear a single signed integer color draw buffer of this framebuffer to the given value.
+    pub fn clear_buffer_i(&self, draw_buffer: u32, value: [i32; 4]) -> Result<()> {
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferiv(gl::COLOR, draw_buffer as _, value.as_ptr());
+            })
+        })
+    }
+
+    /// Clear a single unsigned integer color draw buffer of this framebuffer to the given value.
+    pub fn clear_buffer_u(&self, draw_buffer: u32, value: [u32; 4]) -> Result<()> {
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferuiv(gl::COLOR, draw_buffer as _, value.as_ptr());
+            })
         })
     }
 
+    /// Clear every active color draw buffer of this framebuffer, picking the typed clear matching
+    /// the format of each attachment. Integer attachments are cleared with `value` converted
+    /// component-wise (saturating for unsigned attachments).
+    pub fn clear_all_attachments(&self, value: [f32; 4]) -> Result<()> {
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                for i in 0..Self::max_draw_buffers() {
+                    match draw_buffer_component_type(i) {
+                        None => continue,
+                        Some(gl::INT) => {
+                            let value = value.map(|x| x as i32);
+                            gl::ClearBufferiv(gl::COLOR, i as _, value.as_ptr());
+                        }
+                        Some(gl::UNSIGNED_INT) => {
+                            let value = value.map(|x| x as u32);
+                            gl::ClearBufferuiv(gl::COLOR, i as _, value.as_ptr());
+                        }
+                        Some(_) => gl::ClearBufferfv(gl::COLOR, i as _, value.as_ptr()),
+                    }
+                }
+            })
+        })
+    }
+
+    /// Clear both the depth and stencil buffers of this framebuffer at once.
+    pub fn clear_depth_stencil(&self, depth: f32, stencil: i32) -> Result<()> {
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::ClearBufferfi(gl::DEPTH_STENCIL, 0, depth, stencil);
+            })
+        })
+    }
+
+    /// Set the mapping of normalized device depth values into window depth values.
+    pub fn depth_range(near: f64, far: f64) {
+        unsafe {
+            gl::DepthRange(near, far);
+        }
+    }
+
     pub fn enable_depth_test(func: DepthTestFunction) {
         unsafe {
             gl::DepthFunc(func as _);
@@ -241,6 +476,91 @@ impl Framebuffer {
         unsafe { gl::Disable(gl::DEPTH_TEST) };
     }
 
+    /// Enable or disable linear to sRGB conversion (and blending in linear space) when writing to
+    /// color attachments. This only affects attachments with an sRGB internal format, and the
+    /// backbuffer when the window surface is sRGB-capable.
+    pub fn set_srgb(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            } else {
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+        }
+    }
+
+    /// Enable or disable deriving a coverage mask from the alpha of the first color output, so
+    /// that cutout geometry is anti-aliased by multisampling without sorting. Only has an effect
+    /// on multisampled framebuffers.
+    pub fn enable_alpha_to_coverage(enabled: bool) {
+        unsafe {
+            if enabled {
+                gl::Enable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+            } else {
+                gl::Disable(gl::SAMPLE_ALPHA_TO_COVERAGE);
+            }
+        }
+    }
+
+    /// Enable `GL_SAMPLE_COVERAGE`, combining the coverage of each fragment with a mask covering
+    /// the `value` fraction of the samples, or its complement when `invert` is true.
+    pub fn set_sample_coverage(value: f32, invert: bool) {
+        unsafe {
+            gl::Enable(gl::SAMPLE_COVERAGE);
+            gl::SampleCoverage(value, if invert { gl::TRUE } else { gl::FALSE });
+        }
+    }
+
+    pub fn disable_sample_coverage() {
+        unsafe {
+            gl::Disable(gl::SAMPLE_COVERAGE);
+        }
+    }
+
+    /// Offset the depth of filled polygons by `factor * slope + units * r`, where `r` is the
+    /// smallest resolvable depth difference. Used to fight z-fighting on decals and shadow acne.
+    pub fn enable_polygon_offset(factor: f32, units: f32) {
+        unsafe {
+            gl::PolygonOffset(factor, units);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+        }
+    }
+
+    pub fn disable_polygon_offset() {
+        unsafe { gl::Disable(gl::POLYGON_OFFSET_FILL) };
+    }
+
+    /// Current depth test function, or `None` if depth testing is disabled.
+    pub fn depth_test_state() -> Option<DepthTestFunction> {
+        unsafe {
+            if gl::IsEnabled(gl::DEPTH_TEST) == gl::FALSE {
+                return None;
+            }
+            let mut func = 0;
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut func);
+            DepthTestFunction::from_i32(func)
+        }
+    }
+
+    pub fn enable_stencil_test(func: DepthTestFunction, reference: i32, mask: u32) {
+        unsafe {
+            gl::StencilFunc(func as _, reference, mask);
+            gl::Enable(gl::STENCIL_TEST);
+        }
+    }
+
+    pub fn disable_stencil_test() {
+        unsafe { gl::Disable(gl::STENCIL_TEST) };
+    }
+
+    /// Set the actions taken on the stencil buffer when the stencil test fails, when the stencil
+    /// test passes but the depth test fails, and when both tests pass.
+    pub fn set_stencil_op(stencil_fail: StencilOp, depth_fail: StencilOp, depth_pass: StencilOp) {
+        unsafe {
+            gl::StencilOp(stencil_fail as _, depth_fail as _, depth_pass as _);
+        }
+    }
+
     pub fn enable_blending(source: Blend, target: Blend) {
         unsafe {
             gl::BlendFunc(source as _, target as _);
@@ -248,6 +568,31 @@ impl Framebuffer {
         }
     }
 
+    pub fn enable_blending_separate(
+        source_rgb: Blend,
+        target_rgb: Blend,
+        source_alpha: Blend,
+        target_alpha: Blend,
+    ) {
+        unsafe {
+            gl::BlendFuncSeparate(
+                source_rgb as _,
+                target_rgb as _,
+                source_alpha as _,
+                target_alpha as _,
+            );
+            gl::Enable(gl::BLEND);
+        }
+    }
+
+    /// Set the constant color used by the [`Blend::ConstantColor`] and [`Blend::ConstantAlpha`]
+    /// factors (and their `OneMinus` counterparts).
+    pub fn set_blend_color([red, green, blue, alpha]: [f32; 4]) {
+        unsafe {
+            gl::BlendColor(red, green, blue, alpha);
+        }
+    }
+
     pub fn disable_blending() {
         unsafe {
             gl::BlendFunc(gl::ONE, gl::ZERO);
@@ -255,6 +600,30 @@ impl Framebuffer {
         }
     }
 
+    /// Enable blending on a single draw buffer. Requires OpenGL 4.0.
+    pub fn enable_blending_indexed(buffer: u32, source: Blend, target: Blend) -> Result<()> {
+        eyre::ensure!(
+            gl::BlendFunci::is_loaded(),
+            "Per-draw buffer blending requires OpenGL 4.0"
+        );
+        gl_error_guard(|| unsafe {
+            gl::BlendFunci(buffer, source as _, target as _);
+            gl::Enablei(gl::BLEND, buffer);
+        })
+    }
+
+    /// Disable blending on a single draw buffer. Requires OpenGL 4.0.
+    pub fn disable_blending_indexed(buffer: u32) -> Result<()> {
+        eyre::ensure!(
+            gl::BlendFunci::is_loaded(),
+            "Per-draw buffer blending requires OpenGL 4.0"
+        );
+        gl_error_guard(|| unsafe {
+            gl::BlendFunci(buffer, gl::ONE, gl::ZERO);
+            gl::Disablei(gl::BLEND, buffer);
+        })
+    }
+
     pub fn enable_scissor(x: i32, y: i32, w: i32, h: i32) {
         unsafe {
             gl::Enable(gl::SCISSOR_TEST);
@@ -266,21 +635,21 @@ impl Framebuffer {
         unsafe { gl::Disable(gl::SCISSOR_TEST) }
     }
 
-    pub fn draw(
+    pub fn draw<'a>(
         &self,
-        program: &Program,
+        program: &'a impl ShaderPipeline<'a>,
         vao: &VertexArray,
         mode: DrawMode,
         vertices: Range<i32>,
     ) -> Result<()> {
-        tracing::debug!(
+        hot_log!(debug!(
             "Draw on FBO {} with program {} and VAO {}",
             self.id,
             program.id(),
             vao.id()
-        );
+        ));
         gl_error_guard(|| {
-            program.with_binding(|| {
+            program.with_stages(|| {
                 self.with_binding(|| {
                     vao.with_binding(|| unsafe {
                         gl::DrawArrays(mode as _, vertices.start, vertices.end - vertices.start);
@@ -290,26 +659,187 @@ impl Framebuffer {
         })
     }
 
-    pub fn draw_elements(
+    pub fn draw_elements<'a>(
         &self,
-        program: &Program,
+        program: &'a impl ShaderPipeline<'a>,
         vao: &VertexArray,
         mode: DrawMode,
         slice: Range<i32>,
     ) -> Result<()> {
         let Some(gl_type) = vao.element else { eyre::bail!( "Vertex Array Object needs to be bound to an Element Buffer") };
-        tracing::trace!(
+        hot_log!(trace!(
             "Draw elements on FBO {} with program {} and VAO {}",
             self.id,
             program.id(),
             vao.id()
-        );
-        let count = slice.end - slice.start.max(0);
+        ));
+        let start = slice.start.max(0);
+        let count = slice.end - start;
+        // The last parameter is a byte offset into the element buffer, not an index
+        let offset = start as usize * index_size(gl_type).unwrap_or(1);
+        gl_error_guard(|| {
+            self.with_binding(|| {
+                program.with_stages(|| {
+                    vao.with_binding(|| unsafe {
+                        gl::DrawElements(mode as _, count, gl_type, offset as *const _);
+                    })
+                })
+            })
+        })
+    }
+
+    /// Draw `instances` instances of the vertex range, with instanced attributes starting at
+    /// `base_instance` instead of 0. Requires OpenGL 4.2 or `GL_ARB_base_instance`.
+    pub fn draw_instanced_base_instance<'a>(
+        &self,
+        program: &'a impl ShaderPipeline<'a>,
+        vao: &VertexArray,
+        mode: DrawMode,
+        vertices: Range<i32>,
+        instances: i32,
+        base_instance: u32,
+    ) -> Result<()> {
+        ensure_base_instance_supported()?;
+        hot_log!(debug!(
+            "Draw {} instances from {} on FBO {} with program {} and VAO {}",
+            instances,
+            base_instance,
+            self.id,
+            program.id(),
+            vao.id()
+        ));
+        gl_error_guard(|| {
+            program.with_stages(|| {
+                self.with_binding(|| {
+                    vao.with_binding(|| unsafe {
+                        gl::DrawArraysInstancedBaseInstance(
+                            mode as _,
+                            vertices.start,
+                            vertices.end - vertices.start,
+                            instances,
+                            base_instance,
+                        );
+                    })
+                })
+            })
+        })
+    }
+
+    /// Draw `instances` instances of the element range, with instanced attributes starting at
+    /// `base_instance` instead of 0. Requires OpenGL 4.2 or `GL_ARB_base_instance`.
+    pub fn draw_elements_instanced_base_instance<'a>(
+        &self,
+        program: &'a impl ShaderPipeline<'a>,
+        vao: &VertexArray,
+        mode: DrawMode,
+        slice: Range<i32>,
+        instances: i32,
+        base_instance: u32,
+    ) -> Result<()> {
+        ensure_base_instance_supported()?;
+        let Some(gl_type) = vao.element else { eyre::bail!( "Vertex Array Object needs to be bound to an Element Buffer") };
+        hot_log!(trace!(
+            "Draw {} instances from {} of elements on FBO {} with program {} and VAO {}",
+            instances,
+            base_instance,
+            self.id,
+            program.id(),
+            vao.id()
+        ));
+        let start = slice.start.max(0);
+        let count = slice.end - start;
+        let offset = start as usize * index_size(gl_type).unwrap_or(1);
         gl_error_guard(|| {
             self.with_binding(|| {
-                program.with_binding(|| {
+                program.with_stages(|| {
                     vao.with_binding(|| unsafe {
-                        gl::DrawElements(mode as _, count, gl_type, slice.start as _);
+                        gl::DrawElementsInstancedBaseInstance(
+                            mode as _,
+                            count,
+                            gl_type,
+                            offset as *const _,
+                            instances,
+                            base_instance,
+                        );
+                    })
+                })
+            })
+        })
+    }
+
+    /// Draw the vertex array, using its element buffer when it has one. Draws the whole vertex or
+    /// element buffer when no range is given.
+    pub fn draw_auto<'a>(
+        &self,
+        program: &'a impl ShaderPipeline<'a>,
+        vao: &VertexArray,
+        mode: DrawMode,
+        range: Option<Range<i32>>,
+    ) -> Result<()> {
+        if vao.has_elements() {
+            let range = range.unwrap_or(0..vao.element_count as i32);
+            self.draw_elements(program, vao, mode, range)
+        } else {
+            let range = range.unwrap_or(0..vao.vertex_count as i32);
+            self.draw(program, vao, mode, range)
+        }
+    }
+
+    /// Draw vertices using the draw command found in the indirect buffer at the given byte offset.
+    pub fn draw_indirect<'a>(
+        &self,
+        program: &'a impl ShaderPipeline<'a>,
+        vao: &VertexArray,
+        mode: DrawMode,
+        indirect: &IndirectBuffer<DrawArraysIndirectCommand>,
+        offset: usize,
+    ) -> Result<()> {
+        hot_log!(debug!(
+            "Draw indirect on FBO {} with program {}, VAO {} and command buffer {}",
+            self.id,
+            program.id(),
+            vao.id(),
+            indirect.id
+        ));
+        gl_error_guard(|| {
+            program.with_stages(|| {
+                self.with_binding(|| {
+                    vao.with_binding(|| {
+                        indirect.with_binding(|| unsafe {
+                            gl::DrawArraysIndirect(mode as _, offset as *const _);
+                        })
+                    })
+                })
+            })
+        })
+    }
+
+    /// Draw elements using the draw command found in the indirect buffer at the given byte offset.
+    pub fn draw_elements_indirect<'a>(
+        &self,
+        program: &'a impl ShaderPipeline<'a>,
+        vao: &VertexArray,
+        mode: DrawMode,
+        indirect: &IndirectBuffer<DrawElementsIndirectCommand>,
+        offset: usize,
+    ) -> Result<()> {
+        let Some(gl_type) = vao.element else {
+            eyre::bail!("Vertex Array Object needs to be bound to an Element Buffer")
+        };
+        hot_log!(debug!(
+            "Draw elements indirect on FBO {} with program {}, VAO {} and command buffer {}",
+            self.id,
+            program.id(),
+            vao.id(),
+            indirect.id
+        ));
+        gl_error_guard(|| {
+            program.with_stages(|| {
+                self.with_binding(|| {
+                    vao.with_binding(|| {
+                        indirect.with_binding(|| unsafe {
+                            gl::DrawElementsIndirect(mode as _, gl_type, offset as *const _);
+                        })
                     })
                 })
             })
@@ -317,6 +847,7 @@ impl Framebuffer {
     }
 
     pub fn attach_color<F>(&self, attachment: u8, target: Mipmap<F>) -> Result<()> {
+        ensure_color_attachment(attachment as _)?;
         let texture = target.texture;
         tracing::trace!("glFramebufferTexture{}D(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT_{}, GL_TEXTURE_{}D, {}, 0)",
             texture.dimension().num_dimension(), attachment, texture.dimension().num_dimension(), texture.raw_id());
@@ -332,7 +863,117 @@ impl Framebuffer {
         })
     }
 
-    pub fn attach_depth<D, S>(&self, texture: &Texture<DepthStencil<D, S>>) -> Result<()> {
+    /// Attach a color texture while stating the expected encoding of its internal format, and set
+    /// `GL_FRAMEBUFFER_SRGB` accordingly so that writes to an sRGB attachment are (or aren't)
+    /// converted from linear values by the driver.
+    ///
+    /// Note that `GL_FRAMEBUFFER_SRGB` is global state, and will affect all framebuffers with sRGB
+    /// attachments, not only this one. Errors, leaving the texture attached, when the encoding
+    /// reported by the driver does not match `srgb`.
+    pub fn attach_color_srgb<F>(
+        &self,
+        attachment: u8,
+        target: Mipmap<F>,
+        srgb: bool,
+    ) -> Result<()> {
+        self.attach_color(attachment, target)?;
+        let encoding = self.color_encoding(FramebufferAttachment::Color(attachment as _))?;
+        let expected = if srgb {
+            ColorEncoding::Srgb
+        } else {
+            ColorEncoding::Linear
+        };
+        eyre::ensure!(
+            encoding == expected,
+            "Color attachment {} of framebuffer {} has {:?} encoding, expected {:?}",
+            attachment,
+            self.id,
+            encoding,
+            expected
+        );
+        Self::set_srgb(srgb);
+        Ok(())
+    }
+
+    /// Attach a single Z slice of a 3D texture as a color attachment.
+    pub fn attach_color_3d<F>(&self, attachment: u8, texture: &Texture<F>, z: i32) -> Result<()> {
+        ensure_color_attachment(attachment as _)?;
+        eyre::ensure!(
+            texture.dimension() == Dimension::D3,
+            "Only 3D textures can have a slice attached"
+        );
+        tracing::trace!(
+            "glFramebufferTexture3D(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT_{}, GL_TEXTURE_3D, {}, 0, {})",
+            attachment,
+            texture.raw_id(),
+            z
+        );
+        self.with_binding(|| {
+            gl_error_guard(|| unsafe {
+                gl::FramebufferTexture3D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + attachment as GLenum,
+                    gl::TEXTURE_3D,
+                    texture.raw_id(),
+                    0,
+                    z,
+                );
+            })
+        })
+    }
+
+    /// Attach a single layer of an array, cubemap or 3D texture as a color attachment.
+    pub fn attach_color_layer<F>(
+        &self,
+        attachment: u8,
+        texture: &Texture<F>,
+        layer: u32,
+    ) -> Result<()> {
+        ensure_color_attachment(attachment as _)?;
+        tracing::trace!(
+            "glFramebufferTextureLayer(GL_FRAMEBUFFER, GL_COLOR_ATTACHMENT_{}, {}, 0, {})",
+            attachment,
+            texture.raw_id(),
+            layer
+        );
+        self.with_binding(|| {
+            gl_error_guard(|| unsafe {
+                gl::FramebufferTextureLayer(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + attachment as GLenum,
+                    texture.raw_id(),
+                    0,
+                    layer as _,
+                );
+            })
+        })
+    }
+
+    /// Attach a single layer of an array, cubemap or 3D depth texture into the depth slot.
+    pub fn attach_depth_layer<F: DepthFormat>(
+        &self,
+        texture: &Texture<F>,
+        layer: u32,
+    ) -> Result<()> {
+        tracing::trace!(
+            "glFramebufferTextureLayer(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, {}, 0, {})",
+            texture.raw_id(),
+            layer
+        );
+        self.with_binding(|| {
+            gl_error_guard(|| unsafe {
+                gl::FramebufferTextureLayer(
+                    gl::FRAMEBUFFER,
+                    gl::DEPTH_ATTACHMENT,
+                    texture.raw_id(),
+                    0,
+                    layer as _,
+                );
+            })
+        })
+    }
+
+    pub fn attach_depth<F: DepthFormat>(&self, texture: &Texture<F>) -> Result<()> {
         tracing::trace!(
             "glFramebufferTexture2D(GL_FRAMEBUFFER, GL_DEPTH_ATTACHMENT, GL_TEXTURE_{}D, {}, 0)",
             texture.dimension().num_dimension(),
@@ -351,7 +992,7 @@ impl Framebuffer {
                     Dimension::D2 => gl::FramebufferTexture2D(
                         gl::FRAMEBUFFER,
                         gl::DEPTH_ATTACHMENT,
-                        gl::TEXTURE_2D,
+                        texture.id().target.gl_target(),
                         texture.raw_id(),
                         0,
                     ),
@@ -369,10 +1010,7 @@ impl Framebuffer {
         })
     }
 
-    pub fn attach_depth_stencil<D, S>(
-        &mut self,
-        texture: &Texture<DepthStencil<D, S>>,
-    ) -> Result<()> {
+    pub fn attach_depth_stencil<D, S>(&self, texture: &Texture<DepthStencil<D, S>>) -> Result<()> {
         gl_error_guard(|| {
             self.with_binding(|| unsafe {
                 match texture.dimension() {
@@ -386,7 +1024,7 @@ impl Framebuffer {
                     Dimension::D2 => gl::FramebufferTexture2D(
                         gl::FRAMEBUFFER,
                         gl::DEPTH_STENCIL_ATTACHMENT,
-                        gl::TEXTURE_2D,
+                        texture.id().target.gl_target(),
                         texture.raw_id(),
                         0,
                     ),
@@ -404,11 +1042,113 @@ impl Framebuffer {
         })
     }
 
+    /// Copy a rectangle of this framebuffer into a rectangle of the destination framebuffer.
+    /// Rectangles are given as `[x0, y0, x1, y1]`. Useful to resolve multisampled framebuffers or
+    /// to downsample.
+    pub fn blit_to(
+        &self,
+        dst: &Framebuffer,
+        src: [i32; 4],
+        dst_rect: [i32; 4],
+        mask: ClearBuffer,
+        filter: SampleMode,
+    ) -> Result<()> {
+        let [sx0, sy0, sx1, sy1] = src;
+        let [dx0, dy0, dx1, dy1] = dst_rect;
+        tracing::trace!(
+            "glBlitFramebuffer({:?}, {:?}, {:?}, {:?}) from FBO {} to FBO {}",
+            src,
+            dst_rect,
+            mask,
+            filter,
+            self.id,
+            dst.id
+        );
+        gl_error_guard(|| unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.id.0);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, dst.id.0);
+            gl::BlitFramebuffer(
+                sx0,
+                sy0,
+                sx1,
+                sy1,
+                dx0,
+                dy0,
+                dx1,
+                dy1,
+                mask.bits(),
+                filter as _,
+            );
+            #[cfg(not(feature = "no-unbind"))]
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        })
+    }
+
+    /// Read back a rectangle of pixels, given as `[x, y, width, height]`, from a color attachment
+    /// of this framebuffer. The attachment index is ignored when reading from the backbuffer.
+    pub fn read_pixels<F: TextureFormat>(
+        &self,
+        attachment: u32,
+        rect: [i32; 4],
+    ) -> Result<Vec<F::Subpixel>> {
+        let [x, y, width, height] = rect;
+        eyre::ensure!(
+            width >= 0 && height >= 0,
+            "Cannot read a rectangle with negative size"
+        );
+        let read_buffer = if self.id == FramebufferId::BACKBUFFER {
+            gl::BACK
+        } else {
+            gl::COLOR_ATTACHMENT0 + attachment
+        };
+        let size = width as usize * height as usize * F::COUNT;
+        let mut data = vec![F::Subpixel::zeroed(); size];
+        let row_bytes = width as usize * F::COUNT * std::mem::size_of::<F::Subpixel>();
+        crate::with_pack_alignment(row_alignment(row_bytes), || {
+            gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    gl::ReadBuffer(read_buffer);
+                    gl::ReadPixels(
+                        x,
+                        y,
+                        width,
+                        height,
+                        F::FORMAT,
+                        F::Subpixel::GL_TYPE,
+                        data.as_mut_ptr().cast(),
+                    );
+                })
+            })
+        })?;
+        Ok(data)
+    }
+
+    /// Read back the components of a single pixel from a color attachment of this framebuffer,
+    /// i.e. for object picking.
+    pub fn read_pixel_at<F: TextureFormat>(
+        &self,
+        attachment: u32,
+        x: i32,
+        y: i32,
+    ) -> Result<Vec<F::Subpixel>> {
+        self.read_pixels::<F>(attachment, [x, y, 1, 1])
+    }
+
     pub fn enable_buffers(&self, attachments: impl IntoIterator<Item = u32>) -> Result<()> {
         let symbols = attachments
             .into_iter()
-            .map(|ix| gl::COLOR_ATTACHMENT0 + ix)
-            .collect::<Vec<_>>();
+            .map(|ix| {
+                ensure_color_attachment(ix)?;
+                Ok(gl::COLOR_ATTACHMENT0 + ix)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let max = Self::max_draw_buffers();
+        eyre::ensure!(
+            symbols.len() <= max as usize,
+            "Cannot enable {} draw buffers, the driver supports {} draw buffers",
+            symbols.len(),
+            max
+        );
         gl_error_guard(|| {
             self.with_binding(|| unsafe {
                 gl::DrawBuffers(symbols.len() as _, symbols.as_ptr());
@@ -423,6 +1163,79 @@ impl Framebuffer {
         })
     }
 
+    /// Label this framebuffer for graphics debuggers. The backbuffer cannot be labelled.
+    pub fn set_label(&self, label: &str) {
+        if self.id != FramebufferId::BACKBUFFER {
+            crate::debug::label_object(gl::FRAMEBUFFER, self.id.0, label);
+        }
+    }
+
+    /// Effective number of samples of this framebuffer, as granted by the driver.
+    pub fn samples(&self) -> i32 {
+        self.with_binding(|| unsafe {
+            let mut samples = 0;
+            gl::GetIntegerv(gl::SAMPLES, &mut samples);
+            samples
+        })
+    }
+
+    /// Color encoding of the given attachment. On the backbuffer, this tells whether the window
+    /// surface expects sRGB-encoded values.
+    pub fn color_encoding(&self, attachment: FramebufferAttachment) -> Result<ColorEncoding> {
+        let attachment = attachment.gl_attachment(self.id == FramebufferId::BACKBUFFER);
+        let value = gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                let mut value = 0;
+                gl::GetFramebufferAttachmentParameteriv(
+                    gl::FRAMEBUFFER,
+                    attachment,
+                    gl::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+                    &mut value,
+                );
+                value
+            })
+        })?;
+        ColorEncoding::from_i32(value)
+            .ok_or_else(|| eyre::eyre!("Unknown color encoding {:#x}", value))
+    }
+
+    /// Tell the driver that the contents of the given attachments are no longer needed, saving
+    /// bandwidth on tiled GPUs. Requires OpenGL 4.3.
+    pub fn invalidate(
+        &self,
+        attachments: impl IntoIterator<Item = FramebufferAttachment>,
+    ) -> Result<()> {
+        use FramebufferAttachment::*;
+        eyre::ensure!(
+            gl::InvalidateFramebuffer::is_loaded(),
+            "Invalidating framebuffers requires OpenGL 4.3 or GL_ARB_invalidate_subdata"
+        );
+        let backbuffer = self.id == FramebufferId::BACKBUFFER;
+        let symbols = attachments
+            .into_iter()
+            .flat_map(|attachment| match (attachment, backbuffer) {
+                (Color(_), true) => vec![gl::COLOR],
+                (Depth, true) => vec![gl::DEPTH],
+                (Stencil, true) => vec![gl::STENCIL],
+                (DepthStencil, true) => vec![gl::DEPTH, gl::STENCIL],
+                (attachment, false) => vec![attachment.gl_attachment(false)],
+            })
+            .collect::<Vec<_>>();
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::InvalidateFramebuffer(gl::FRAMEBUFFER, symbols.len() as _, symbols.as_ptr());
+            })
+        })
+    }
+
+    /// Invalidate attachments of the backbuffer. See [`Self::invalidate`]; also requires OpenGL
+    /// 4.3.
+    pub fn invalidate_backbuffer(
+        attachments: impl IntoIterator<Item = FramebufferAttachment>,
+    ) -> Result<()> {
+        Self::backbuffer().invalidate(attachments)
+    }
+
     pub fn assert_complete(&self) -> Result<()> {
         match self.check_status() {
             FramebufferStatus::Complete => Ok(()),
@@ -431,6 +1244,207 @@ impl Framebuffer {
     }
 }
 
+/// Builder for framebuffers, keeping track of the color attachments so that the draw buffers are
+/// enabled accordingly, and checking the framebuffer for completeness once done.
+///
+/// As with [`Framebuffer`], the attached textures are only borrowed and must outlive the
+/// framebuffer.
+#[derive(Debug)]
+pub struct FramebufferBuilder {
+    framebuffer: Framebuffer,
+    color_attachments: Vec<u32>,
+}
+
+impl FramebufferBuilder {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            framebuffer: Framebuffer::new(),
+            color_attachments: vec![],
+        }
+    }
+
+    /// Attach a color texture at the given index. Attaching twice at the same index replaces the
+    /// previous attachment.
+    pub fn with_color<F>(mut self, attachment: u8, target: Mipmap<F>) -> Result<Self> {
+        self.framebuffer.attach_color(attachment, target)?;
+        if !self.color_attachments.contains(&(attachment as u32)) {
+            self.color_attachments.push(attachment as u32);
+        }
+        Ok(self)
+    }
+
+    pub fn with_depth<F: DepthFormat>(self, texture: &Texture<F>) -> Result<Self> {
+        self.framebuffer.attach_depth(texture)?;
+        Ok(self)
+    }
+
+    pub fn with_depth_stencil<D, S>(self, texture: &Texture<DepthStencil<D, S>>) -> Result<Self> {
+        self.framebuffer.attach_depth_stencil(texture)?;
+        Ok(self)
+    }
+
+    /// Number of color attachments added so far.
+    pub fn num_color_attachments(&self) -> usize {
+        self.color_attachments.len()
+    }
+
+    /// Enable drawing into all the color attachments, in increasing index order, and return the
+    /// framebuffer if it is complete.
+    pub fn build(mut self) -> Result<Framebuffer> {
+        self.color_attachments.sort_unstable();
+        self.framebuffer
+            .enable_buffers(self.color_attachments.iter().copied())?;
+        self.framebuffer.assert_complete()?;
+        Ok(self.framebuffer)
+    }
+}
+
+/// Framebuffer owning a multisampled color and depth attachment, for rendering with anti-aliasing.
+/// The result has to be resolved into a single-sampled framebuffer with
+/// [`resolve_to`](Self::resolve_to) before being sampled or presented.
+#[derive(Debug)]
+pub struct MultisampleFramebuffer<C, D> {
+    framebuffer: Framebuffer,
+    color: Texture<C>,
+    depth: Texture<D>,
+}
+
+impl<C, D> std::ops::Deref for MultisampleFramebuffer<C, D> {
+    type Target = Framebuffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.framebuffer
+    }
+}
+
+impl<C: TextureFormat, D: DepthFormat> MultisampleFramebuffer<C, D> {
+    /// Allocate multisampled color and depth textures of the given size and attach them to a new
+    /// framebuffer. The driver may grant less samples than requested, see
+    /// [`Texture::allocated_samples`].
+    pub fn new(width: NonZeroU32, height: NonZeroU32, samples: NonZeroU32) -> Result<Self> {
+        let one = NonZeroU32::new(1).unwrap();
+        let color = Texture::new_multisampled(width, height, one, Dimension::D2, samples);
+        color.reserve_memory()?;
+        let depth = Texture::new_multisampled(width, height, one, Dimension::D2, samples);
+        depth.reserve_memory()?;
+
+        let framebuffer = FramebufferBuilder::new()
+            .with_color(0, color.mipmap(0)?)?
+            .with_depth(&depth)?
+            .build()?;
+        Ok(Self {
+            framebuffer,
+            color,
+            depth,
+        })
+    }
+
+    pub fn color(&self) -> &Texture<C> {
+        &self.color
+    }
+
+    pub fn depth(&self) -> &Texture<D> {
+        &self.depth
+    }
+
+    pub fn size(&self) -> (NonZeroU32, NonZeroU32) {
+        let (width, height, _) = self.color.size();
+        (width, height)
+    }
+
+    /// Resolve the color attachment into the destination framebuffer, which needs to be of the
+    /// same size. Depth is not resolved.
+    pub fn resolve_to(&self, dst: &Framebuffer) -> Result<()> {
+        let (width, height) = self.size();
+        let rect = [0, 0, width.get() as _, height.get() as _];
+        self.framebuffer
+            .blit_to(dst, rect, rect, ClearBuffer::COLOR, SampleMode::Nearest)
+    }
+}
+
+/// Draw a single triangle covering the whole viewport with the given program, without any vertex
+/// buffer. The vertex shader is expected to generate the vertices from `gl_VertexID`, for example:
+///
+/// ```glsl
+/// out vec2 v_uv;
+///
+/// void main() {
+///     v_uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+///     gl_Position = vec4(v_uv * 2.0 - 1.0, 0.0, 1.0);
+/// }
+/// ```
+///
+/// See [`fullscreen_quad_vao`](crate::vertex::fullscreen_quad_vao) for a quad with explicit
+/// position and UV attributes instead.
+pub fn draw_fullscreen_quad<'a>(
+    fbo: &Framebuffer,
+    program: &'a impl ShaderPipeline<'a>,
+) -> Result<()> {
+    fbo.draw(program, fullscreen_vao()?, DrawMode::Triangles, 0..3)
+}
+
+static MAX_COLOR_ATTACHMENTS: Lazy<u32> =
+    Lazy::new(|| query::get_i32(gl::MAX_COLOR_ATTACHMENTS) as _);
+
+static MAX_DRAW_BUFFERS: Lazy<u32> = Lazy::new(|| query::get_i32(gl::MAX_DRAW_BUFFERS) as _);
+
+static GL_VERSION: Lazy<(i32, i32)> = Lazy::new(|| {
+    (
+        query::get_i32(gl::MAJOR_VERSION),
+        query::get_i32(gl::MINOR_VERSION),
+    )
+});
+
+static BASE_INSTANCE_SUPPORTED: Lazy<bool> =
+    Lazy::new(|| *GL_VERSION >= (4, 2) || crate::has_extension("GL_ARB_base_instance"));
+
+fn ensure_base_instance_supported() -> Result<()> {
+    eyre::ensure!(
+        *BASE_INSTANCE_SUPPORTED,
+        "Base instance draws require OpenGL 4.2 or GL_ARB_base_instance, context is OpenGL {}.{}",
+        GL_VERSION.0,
+        GL_VERSION.1
+    );
+    Ok(())
+}
+
+fn ensure_color_attachment(attachment: u32) -> Result<()> {
+    let max = Framebuffer::max_color_attachments();
+    eyre::ensure!(
+        attachment < max,
+        "Color attachment {} is out of range, the driver supports {} color attachments",
+        attachment,
+        max
+    );
+    Ok(())
+}
+
+/// Component type of the attachment behind the given draw buffer of the currently bound draw
+/// framebuffer, or `None` if the draw buffer is not in use. The backbuffer is always `GL_FLOAT`.
+fn draw_buffer_component_type(draw_buffer: u32) -> Option<GLenum> {
+    unsafe {
+        let mut attachment = 0;
+        gl::GetIntegerv(gl::DRAW_BUFFER0 + draw_buffer, &mut attachment);
+        if attachment as GLenum == gl::NONE {
+            return None;
+        }
+        let mut framebuffer = 0;
+        gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+        if framebuffer == 0 {
+            return Some(gl::FLOAT);
+        }
+        let mut component_type = 0;
+        gl::GetFramebufferAttachmentParameteriv(
+            gl::DRAW_FRAMEBUFFER,
+            attachment as _,
+            gl::FRAMEBUFFER_ATTACHMENT_COMPONENT_TYPE,
+            &mut component_type,
+        );
+        Some(component_type as _)
+    }
+}
+
 fn normalize_range<B: RangeBounds<i32>>(bounds: B, limit: Range<i32>) -> Range<i32> {
     use std::ops::Bound;
 
@@ -446,3 +1460,35 @@ fn normalize_range<B: RangeBounds<i32>>(bounds: B, limit: Range<i32>) -> Range<i
     };
     (start.max(limit.start))..(end.min(limit.end))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_range_included_bounds() {
+        assert_eq!(normalize_range(2..=5, 0..10), 2..6);
+    }
+
+    #[test]
+    fn normalize_range_excluded_bounds() {
+        use std::ops::Bound;
+        assert_eq!(normalize_range(2..5, 0..10), 2..5);
+        assert_eq!(
+            normalize_range((Bound::Excluded(2), Bound::Excluded(5)), 0..10),
+            3..5
+        );
+    }
+
+    #[test]
+    fn normalize_range_unbounded_uses_limit() {
+        assert_eq!(normalize_range(.., 0..10), 0..10);
+        assert_eq!(normalize_range(3.., 0..10), 3..10);
+        assert_eq!(normalize_range(..4, 0..10), 0..4);
+    }
+
+    #[test]
+    fn normalize_range_clamps_to_limit() {
+        assert_eq!(normalize_range(-5..=20, 0..10), 0..10);
+    }
+}
@@ -1,28 +1,56 @@
 use std::{
+    collections::HashSet,
     ffi::{c_void, CStr},
     ops::Not,
 };
 
 use eyre::{Context, ContextCompat, Result};
-use gl::types::GLenum;
+use gl::types::{GLenum, GLuint};
 use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use once_cell::sync::OnceCell;
 
 use utils::gl_error_guard;
 
 pub use gl;
 
+/// Logging on hot paths (bindings, uniform writes, draw calls). These are compiled out entirely
+/// with the `fast` feature, instead of only being filtered at runtime by the subscriber.
+macro_rules! hot_log {
+    ($level:ident!($($arg:tt)*)) => {
+        #[cfg(not(feature = "fast"))]
+        tracing::$level!($($arg)*);
+    };
+}
+
 pub mod base;
 pub mod buffer;
 pub mod debug;
 pub mod framebuffer;
 pub mod program;
+pub mod query;
 pub mod shader;
+pub mod sync;
 pub mod texture;
+pub mod timer;
 mod utils;
 pub mod vertex;
 
-pub fn load_with(loader: impl FnMut(&'static str) -> *const c_void) {
-    gl::load_with(loader)
+/// Load the OpenGL function pointers, as well as the extension functions used by this crate,
+/// with the given loader (i.e. `glfw::Window::get_proc_address` or `glutin`'s equivalent).
+///
+/// # Testing without a driver
+///
+/// Helpers which only compute or validate values (range normalization, alignment, vertex
+/// descriptions, format and binding constants) don't touch OpenGL and can be unit tested without
+/// calling this function at all. For code that does call into OpenGL, load with a stub loader
+/// returning null pointers, `load_with(|_| std::ptr::null())`: each OpenGL function then reports
+/// `is_loaded() == false` and panics if called, which makes unexpected GL calls in the code under
+/// test fail loudly instead of crashing in the driver.
+pub fn load_with(mut loader: impl FnMut(&'static str) -> *const c_void) {
+    gl::load_with(&mut loader);
+    shader::load_extensions_with(&mut loader);
+    texture::load_extensions_with(&mut loader);
 }
 
 pub fn line_width() -> f32 {
@@ -33,8 +61,33 @@ pub fn line_width() -> f32 {
     }
 }
 
-pub fn set_line_width(width: f32) {
-    unsafe { gl::LineWidth(width) }
+/// Range of line widths supported by the driver, for the current line smoothing state. Core
+/// profile contexts commonly only support a width of 1 for aliased lines.
+pub fn line_width_range() -> (f32, f32) {
+    unsafe {
+        let pname = if gl::IsEnabled(gl::LINE_SMOOTH) == gl::TRUE {
+            gl::SMOOTH_LINE_WIDTH_RANGE
+        } else {
+            gl::ALIASED_LINE_WIDTH_RANGE
+        };
+        let mut range = [0f32; 2];
+        gl::GetFloatv(pname, range.as_mut_ptr());
+        (range[0], range[1])
+    }
+}
+
+/// Set the line width. Returns an error, leaving the line width untouched, when the width is
+/// outside of [`line_width_range`], as the driver would otherwise silently clamp it.
+pub fn set_line_width(width: f32) -> Result<()> {
+    let (min, max) = line_width_range();
+    eyre::ensure!(
+        (min..=max).contains(&width),
+        "Line width {} is outside of the supported range {}..={}",
+        width,
+        min,
+        max
+    );
+    gl_error_guard(|| unsafe { gl::LineWidth(width) })
 }
 
 pub fn set_line_smooth(smooth: bool) {
@@ -45,6 +98,83 @@ pub fn set_line_smooth(smooth: bool) {
     }
 }
 
+/// Set the row alignment of pixel data read from client memory by texture uploads
+/// (`GL_UNPACK_ALIGNMENT`). Valid values are 1, 2, 4 and 8; the driver default is 4.
+///
+/// Texture uploads of this crate temporarily set it to match their tightly packed data, and
+/// restore the previous value afterwards.
+pub fn set_unpack_alignment(n: i32) -> Result<()> {
+    ensure_alignment(n)?;
+    gl_error_guard(|| unsafe { gl::PixelStorei(gl::UNPACK_ALIGNMENT, n) })
+}
+
+/// Set the row alignment of pixel data written to client memory by pixel downloads
+/// (`GL_PACK_ALIGNMENT`). Valid values are 1, 2, 4 and 8; the driver default is 4.
+///
+/// Pixel downloads of this crate temporarily set it to match their tightly packed data, and
+/// restore the previous value afterwards.
+pub fn set_pack_alignment(n: i32) -> Result<()> {
+    ensure_alignment(n)?;
+    gl_error_guard(|| unsafe { gl::PixelStorei(gl::PACK_ALIGNMENT, n) })
+}
+
+/// Run the callback with `GL_UNPACK_ALIGNMENT` set to `n`, restoring the previous alignment
+/// afterwards.
+pub(crate) fn with_unpack_alignment<T>(n: i32, cb: impl FnOnce() -> Result<T>) -> Result<T> {
+    with_pixel_store(gl::UNPACK_ALIGNMENT, n, cb)
+}
+
+/// Run the callback with `GL_PACK_ALIGNMENT` set to `n`, restoring the previous alignment
+/// afterwards.
+pub(crate) fn with_pack_alignment<T>(n: i32, cb: impl FnOnce() -> Result<T>) -> Result<T> {
+    with_pixel_store(gl::PACK_ALIGNMENT, n, cb)
+}
+
+fn with_pixel_store<T>(pname: GLenum, n: i32, cb: impl FnOnce() -> Result<T>) -> Result<T> {
+    ensure_alignment(n)?;
+    let previous = query::get_i32(pname);
+    if previous == n {
+        return cb();
+    }
+    gl_error_guard(|| unsafe { gl::PixelStorei(pname, n) })?;
+    let ret = cb();
+    unsafe { gl::PixelStorei(pname, previous) };
+    ret
+}
+
+fn ensure_alignment(n: i32) -> Result<()> {
+    eyre::ensure!(
+        matches!(n, 1 | 2 | 4 | 8),
+        "Pixel row alignment must be 1, 2, 4 or 8, got {}",
+        n
+    );
+    Ok(())
+}
+
+/// Set the number of vertices making up a patch, when drawing with [`vertex::DrawMode::Patches`]
+/// for tessellation.
+pub fn set_patch_vertices(n: i32) -> Result<()> {
+    gl_error_guard(|| unsafe { gl::PatchParameteri(gl::PATCH_VERTICES, n) })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[repr(u32)]
+pub enum PolygonMode {
+    Fill = gl::FILL,
+    Line = gl::LINE,
+    Point = gl::POINT,
+}
+
+/// Set how polygons are rasterized, i.e. as wireframes with [`PolygonMode::Line`]. Core profile
+/// contexts only accept [`Cull::FrontAndBack`] as the face; other faces return an error.
+pub fn set_polygon_mode(face: Cull, mode: PolygonMode) -> Result<()> {
+    eyre::ensure!(
+        face == Cull::FrontAndBack,
+        "Polygon mode can only be set for front and back faces at once in core profile"
+    );
+    gl_error_guard(|| unsafe { gl::PolygonMode(face as _, mode as _) })
+}
+
 pub fn point_size() -> f32 {
     unsafe {
         let mut value = 0.;
@@ -68,11 +198,83 @@ pub fn get_string(of: GLenum) -> Result<String> {
     .and_then(|res| res)
 }
 
+static EXTENSIONS: OnceCell<HashSet<String>> = OnceCell::new();
+
+/// Extensions supported by the OpenGL context. The list is queried on first call and cached for
+/// the lifetime of the program, assuming all contexts share the same driver.
+pub fn supported_extensions() -> &'static HashSet<String> {
+    EXTENSIONS.get_or_init(|| unsafe {
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as GLuint)
+            .filter_map(|ix| {
+                let ext = gl::GetStringi(gl::EXTENSIONS, ix);
+                (!ext.is_null()).then(|| CStr::from_ptr(ext.cast()).to_string_lossy().to_string())
+            })
+            .collect()
+    })
+}
+
+/// Returns true when the OpenGL context supports the given extension, i.e.
+/// `GL_ARB_sparse_texture`.
+pub fn has_extension(name: &str) -> bool {
+    supported_extensions().contains(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContextProfile {
+    Core,
+    Compatibility,
+}
+
+/// Version and driver information of the current OpenGL context.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextInfo {
+    pub major: u32,
+    pub minor: u32,
+    /// Profile of the context, or `None` for contexts older than OpenGL 3.2.
+    pub profile: Option<ContextProfile>,
+    pub vendor: String,
+    pub renderer: String,
+    pub glsl_version: String,
+}
+
+/// Query the version and driver information of the current OpenGL context.
+pub fn context_info() -> Result<ContextInfo> {
+    let (major, minor, profile_mask) = gl_error_guard(|| unsafe {
+        let mut major = 0;
+        let mut minor = 0;
+        let mut profile_mask = 0;
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        if (major, minor) >= (3, 2) {
+            gl::GetIntegerv(gl::CONTEXT_PROFILE_MASK, &mut profile_mask);
+        }
+        (major, minor, profile_mask as GLenum)
+    })?;
+    let profile = if profile_mask & gl::CONTEXT_CORE_PROFILE_BIT != 0 {
+        Some(ContextProfile::Core)
+    } else if profile_mask & gl::CONTEXT_COMPATIBILITY_PROFILE_BIT != 0 {
+        Some(ContextProfile::Compatibility)
+    } else {
+        None
+    };
+    Ok(ContextInfo {
+        major: major as _,
+        minor: minor as _,
+        profile,
+        vendor: get_string(gl::VENDOR)?,
+        renderer: get_string(gl::RENDERER)?,
+        glsl_version: get_string(gl::SHADING_LANGUAGE_VERSION)?,
+    })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[repr(u32)]
 pub enum Cull {
     Front = gl::FRONT,
     Back = gl::BACK,
+    FrontAndBack = gl::FRONT_AND_BACK,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
@@ -99,3 +301,67 @@ pub fn culling(mode: impl Into<Option<Cull>>) {
 pub fn set_front_face(front_face: FrontFace) {
     gl_error_guard(|| unsafe { gl::FrontFace(front_face as _) }).unwrap();
 }
+
+/// Face culling state of the OpenGL context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CullState {
+    pub enabled: bool,
+    pub mode: Cull,
+    pub front_face: FrontFace,
+}
+
+impl CullState {
+    /// Query the current culling state.
+    pub fn current() -> Self {
+        let (enabled, mode, front_face) = unsafe {
+            let mut mode = 0;
+            let mut front_face = 0;
+            gl::GetIntegerv(gl::CULL_FACE_MODE, &mut mode);
+            gl::GetIntegerv(gl::FRONT_FACE, &mut front_face);
+            (gl::IsEnabled(gl::CULL_FACE) == gl::TRUE, mode, front_face)
+        };
+        Self {
+            enabled,
+            mode: Cull::from_i32(mode).unwrap(),
+            front_face: FrontFace::from_i32(front_face).unwrap(),
+        }
+    }
+
+    /// Apply this culling state to the OpenGL context.
+    pub fn apply(&self) {
+        gl_error_guard(|| unsafe {
+            gl::CullFace(self.mode as _);
+            if self.enabled {
+                gl::Enable(gl::CULL_FACE);
+            } else {
+                gl::Disable(gl::CULL_FACE);
+            }
+        })
+        .unwrap();
+        set_front_face(self.front_face);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[repr(u32)]
+pub enum ClipOrigin {
+    LowerLeft = gl::LOWER_LEFT,
+    UpperLeft = gl::UPPER_LEFT,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+#[repr(u32)]
+pub enum ClipDepth {
+    NegativeOneToOne = gl::NEGATIVE_ONE_TO_ONE,
+    ZeroToOne = gl::ZERO_TO_ONE,
+}
+
+/// Set the clip space conventions. Using [`ClipDepth::ZeroToOne`] with a greater-than depth test
+/// and a depth buffer cleared to 0 enables a reverse-Z pipeline. Requires OpenGL 4.5.
+pub fn set_clip_control(origin: ClipOrigin, depth: ClipDepth) -> Result<()> {
+    eyre::ensure!(
+        gl::ClipControl::is_loaded(),
+        "Clip control requires OpenGL 4.5"
+    );
+    gl_error_guard(|| unsafe { gl::ClipControl(origin as _, depth as _) })
+}
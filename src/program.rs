@@ -7,10 +7,11 @@ use std::{
     path::Path,
 };
 
+use bitflags::bitflags;
 use duplicate::duplicate_item as duplicate;
 use either::Either;
 use eyre::{Context, Result};
-use gl::types::{GLdouble, GLenum, GLfloat, GLint, GLuint};
+use gl::types::{GLdouble, GLenum, GLfloat, GLint, GLsizei, GLuint};
 
 use crate::{
     base::{
@@ -18,14 +19,35 @@ use crate::{
         GlType,
     },
     buffer::BufferSlice,
-    shader::{FragmentShader, GeometryShader, ShaderId, VertexShader},
+    shader::{
+        create_spirv_shader, FragmentShader, GeometryShader, Shader, ShaderId, ShaderStage,
+        VertexShader,
+    },
     utils::{gl_error_guard, gl_string},
+    vertex::VertexAttributes,
 };
 
 /// Trait of types that can be written into shader uniforms. This allows polymorphic use of the
 /// methods on [`ActiveProgram`](struct::ActiveProgram);
 pub trait Uniform {
     unsafe fn write_uniform(&self, location: GLint);
+
+    /// Write an array of values starting at the given location. The default implementation
+    /// writes each element at consecutive locations; basic types override this with a single
+    /// array upload.
+    ///
+    /// # Safety
+    ///
+    /// The program owning `location` must be in use, and `location` must be a uniform whose type
+    /// and array length match `values`.
+    unsafe fn write_uniform_array(values: &[Self], location: GLint)
+    where
+        Self: Sized,
+    {
+        for (i, value) in values.iter().enumerate() {
+            value.write_uniform(location + i as GLint);
+        }
+    }
 }
 
 impl Uniform for bool {
@@ -35,16 +57,20 @@ impl Uniform for bool {
 }
 
 #[duplicate(
-gl_t            uniform;
-[GLint]         [Uniform1i];
-[GLuint]        [Uniform1ui];
-[GLfloat]       [Uniform1f];
-[GLdouble]      [Uniform1d];
+gl_t            uniform         uniform_v;
+[GLint]         [Uniform1i]     [Uniform1iv];
+[GLuint]        [Uniform1ui]    [Uniform1uiv];
+[GLfloat]       [Uniform1f]     [Uniform1fv];
+[GLdouble]      [Uniform1d]     [Uniform1dv];
 )]
 impl Uniform for gl_t {
     unsafe fn write_uniform(&self, location: GLint) {
         gl::uniform(location, *self)
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform_v(location, values.len() as _, values.as_ptr())
+    }
 }
 
 #[duplicate(
@@ -61,45 +87,57 @@ impl Uniform for &[gl_t] {
 }
 
 #[duplicate(
-gl_t        uniform;
-[GLint]     [Uniform2i];
-[GLuint]    [Uniform2ui];
-[GLfloat]   [Uniform2f];
-[GLdouble]  [Uniform2d];
+gl_t        uniform         uniform_v;
+[GLint]     [Uniform2i]     [Uniform2iv];
+[GLuint]    [Uniform2ui]    [Uniform2uiv];
+[GLfloat]   [Uniform2f]     [Uniform2fv];
+[GLdouble]  [Uniform2d]     [Uniform2dv];
 )]
 impl Uniform for [gl_t; 2] {
     unsafe fn write_uniform(&self, location: GLint) {
         let [x, y] = *self;
         gl::uniform(location, x, y);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform_v(location, values.len() as _, values.as_ptr().cast())
+    }
 }
 
 #[duplicate(
-gl_t        uniform;
-[GLint]     [Uniform3i];
-[GLuint]    [Uniform3ui];
-[GLfloat]   [Uniform3f];
-[GLdouble]  [Uniform3d];
+gl_t        uniform         uniform_v;
+[GLint]     [Uniform3i]     [Uniform3iv];
+[GLuint]    [Uniform3ui]    [Uniform3uiv];
+[GLfloat]   [Uniform3f]     [Uniform3fv];
+[GLdouble]  [Uniform3d]     [Uniform3dv];
 )]
 impl Uniform for [gl_t; 3] {
     unsafe fn write_uniform(&self, location: GLint) {
         let [x, y, z] = *self;
         gl::uniform(location, x, y, z);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform_v(location, values.len() as _, values.as_ptr().cast())
+    }
 }
 
 #[duplicate(
-gl_t        uniform;
-[GLint]     [Uniform4i];
-[GLuint]    [Uniform4ui];
-[GLfloat]   [Uniform4f];
-[GLdouble]  [Uniform4d];
+gl_t        uniform         uniform_v;
+[GLint]     [Uniform4i]     [Uniform4iv];
+[GLuint]    [Uniform4ui]    [Uniform4uiv];
+[GLfloat]   [Uniform4f]     [Uniform4fv];
+[GLdouble]  [Uniform4d]     [Uniform4dv];
 )]
 impl Uniform for [gl_t; 4] {
     unsafe fn write_uniform(&self, location: GLint) {
         let [x, y, z, w] = *self;
         gl::uniform(location, x, y, z, w);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform_v(location, values.len() as _, values.as_ptr().cast())
+    }
 }
 
 #[duplicate(
@@ -111,6 +149,15 @@ impl Uniform for [[gl_t; 2]; 2] {
     unsafe fn write_uniform(&self, location: GLint) {
         gl::uniform(location, 1, gl::FALSE as _, self.as_ptr() as *const _);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform(
+            location,
+            values.len() as _,
+            gl::FALSE as _,
+            values.as_ptr() as *const _,
+        );
+    }
 }
 
 #[duplicate(
@@ -122,6 +169,15 @@ impl Uniform for [[gl_t; 3]; 3] {
     unsafe fn write_uniform(&self, location: GLint) {
         gl::uniform(location, 1, gl::FALSE as _, self.as_ptr() as *const _);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform(
+            location,
+            values.len() as _,
+            gl::FALSE as _,
+            values.as_ptr() as *const _,
+        );
+    }
 }
 
 #[duplicate(
@@ -133,6 +189,15 @@ impl Uniform for [[gl_t; 4]; 4] {
     unsafe fn write_uniform(&self, location: GLint) {
         gl::uniform(location, 1, gl::FALSE as _, self.as_ptr() as *const _);
     }
+
+    unsafe fn write_uniform_array(values: &[Self], location: GLint) {
+        gl::uniform(
+            location,
+            values.len() as _,
+            gl::FALSE as _,
+            values.as_ptr() as *const _,
+        );
+    }
 }
 
 #[cfg(feature = "uniforms-glam")]
@@ -181,6 +246,57 @@ impl Uniform for &[glam_t] {
     }
 }
 
+#[cfg(feature = "uniforms-nalgebra")]
+#[duplicate(
+nalgebra_t;
+[nalgebra::Vector2<f32>];
+[nalgebra::Vector2<f64>];
+[nalgebra::Vector3<f32>];
+[nalgebra::Vector3<f64>];
+[nalgebra::Vector4<f32>];
+[nalgebra::Vector4<f64>];
+)]
+impl Uniform for nalgebra_t {
+    unsafe fn write_uniform(&self, location: GLint) {
+        // Vectors are stored as a single column array
+        self.data.0[0].write_uniform(location);
+    }
+}
+
+#[cfg(feature = "uniforms-nalgebra")]
+#[duplicate(
+nalgebra_t                  uniform;
+[nalgebra::Matrix2<f32>]    [UniformMatrix2fv];
+[nalgebra::Matrix3<f32>]    [UniformMatrix3fv];
+[nalgebra::Matrix4<f32>]    [UniformMatrix4fv];
+[nalgebra::Matrix2<f64>]    [UniformMatrix2dv];
+[nalgebra::Matrix3<f64>]    [UniformMatrix3dv];
+[nalgebra::Matrix4<f64>]    [UniformMatrix4dv];
+)]
+impl Uniform for nalgebra_t {
+    unsafe fn write_uniform(&self, location: GLint) {
+        // nalgebra stores matrices in column-major order, as OpenGL expects
+        gl::uniform(location, 1, gl::FALSE as _, self.as_slice().as_ptr());
+    }
+}
+
+#[cfg(feature = "uniforms-mint")]
+#[duplicate(
+mint_t                          array_t;
+[mint::Vector2<f32>]            [[f32; 2]];
+[mint::Vector3<f32>]            [[f32; 3]];
+[mint::Vector4<f32>]            [[f32; 4]];
+[mint::ColumnMatrix2<f32>]      [[[f32; 2]; 2]];
+[mint::ColumnMatrix3<f32>]      [[[f32; 3]; 3]];
+[mint::ColumnMatrix4<f32>]      [[[f32; 4]; 4]];
+)]
+impl Uniform for mint_t {
+    unsafe fn write_uniform(&self, location: GLint) {
+        let array: array_t = (*self).into();
+        array.write_uniform(location);
+    }
+}
+
 impl<L: Uniform, R: Uniform> Uniform for Either<L, R> {
     unsafe fn write_uniform(&self, location: GLint) {
         match self {
@@ -198,6 +314,66 @@ impl<T: Uniform> Uniform for Option<T> {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// GLSL type of an uniform, used to upload uniforms from raw bytes.
+pub enum UniformKind {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Mat2,
+    Mat3,
+    Mat4,
+}
+
+impl UniformKind {
+    /// Number of 4-byte components in one value of this kind.
+    pub fn num_components(&self) -> usize {
+        use UniformKind::*;
+        match self {
+            Float | Int | UInt => 1,
+            Vec2 | IVec2 | UVec2 => 2,
+            Vec3 | IVec3 | UVec3 => 3,
+            Vec4 | IVec4 | UVec4 | Mat2 => 4,
+            Mat3 => 9,
+            Mat4 => 16,
+        }
+    }
+
+    /// Write `count` values of this kind read from `data` into the uniform at `location`.
+    unsafe fn write_raw(&self, location: GLint, count: GLint, data: &[u32]) {
+        use UniformKind::*;
+        let float = data.as_ptr() as *const GLfloat;
+        let int = data.as_ptr() as *const GLint;
+        let uint = data.as_ptr();
+        match self {
+            Float => gl::Uniform1fv(location, count, float),
+            Vec2 => gl::Uniform2fv(location, count, float),
+            Vec3 => gl::Uniform3fv(location, count, float),
+            Vec4 => gl::Uniform4fv(location, count, float),
+            Int => gl::Uniform1iv(location, count, int),
+            IVec2 => gl::Uniform2iv(location, count, int),
+            IVec3 => gl::Uniform3iv(location, count, int),
+            IVec4 => gl::Uniform4iv(location, count, int),
+            UInt => gl::Uniform1uiv(location, count, uint),
+            UVec2 => gl::Uniform2uiv(location, count, uint),
+            UVec3 => gl::Uniform3uiv(location, count, uint),
+            UVec4 => gl::Uniform4uiv(location, count, uint),
+            Mat2 => gl::UniformMatrix2fv(location, count, gl::FALSE, float),
+            Mat3 => gl::UniformMatrix3fv(location, count, gl::FALSE, float),
+            Mat4 => gl::UniformMatrix4fv(location, count, gl::FALSE, float),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 /// Structure allowing uniforms to be written into a program.
 pub struct UniformLocation {
@@ -235,6 +411,14 @@ impl UniformBlockIndex {
     }
 }
 
+#[derive(Debug, Clone)]
+/// Member of an uniform block, as laid out by the driver.
+pub struct UniformBlockMember {
+    pub name: Cow<'static, str>,
+    /// Offset in bytes from the start of the block.
+    pub offset: usize,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
 /// Program ID newtype. Guaranteed to be non-zero if it exists. Allows `Option<ProgramId>` to coerce
@@ -336,6 +520,49 @@ impl Program<Unlinked> {
         self
     }
 
+    /// Assign an attribute to a fixed location. Takes effect when the program is linked.
+    pub fn bind_attribute_location(&mut self, index: u32, name: &str) {
+        tracing::trace!(
+            "glBindAttribLocation({}, {}, {:?})",
+            self.id.get(),
+            index,
+            name
+        );
+        let name = CString::new(name).unwrap();
+        unsafe { gl::BindAttribLocation(self.id.get(), index, name.as_ptr()) };
+    }
+
+    /// Mark the program as separable, allowing its stages to be used in a [`ProgramPipeline`].
+    pub fn separable(self) -> Self {
+        tracing::trace!(
+            "glProgramParameteri({}, GL_PROGRAM_SEPARABLE, GL_TRUE)",
+            self.id.get()
+        );
+        unsafe { gl::ProgramParameteri(self.id.get(), gl::PROGRAM_SEPARABLE, gl::TRUE as _) };
+        self
+    }
+
+    /// Allow retrieving the binary of the program once linked, with [`Program::get_binary`].
+    /// Requires OpenGL 4.1 or `GL_ARB_get_program_binary`.
+    pub fn retrievable(self) -> Result<Self> {
+        eyre::ensure!(
+            gl::ProgramParameteri::is_loaded(),
+            "Program binaries require OpenGL 4.1 or GL_ARB_get_program_binary"
+        );
+        tracing::trace!(
+            "glProgramParameteri({}, GL_PROGRAM_BINARY_RETRIEVABLE_HINT, GL_TRUE)",
+            self.id.get()
+        );
+        unsafe {
+            gl::ProgramParameteri(
+                self.id.get(),
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as _,
+            )
+        };
+        Ok(self)
+    }
+
     /// Link the program.
     pub fn link(self) -> Result<Program> {
         let id = self.id.get();
@@ -391,6 +618,108 @@ impl<'a> Resource<'a> for Program {
 }
 
 impl Program<Linked> {
+    /// Create a separable program containing a single shader stage, to be combined with other
+    /// separable programs in a [`ProgramPipeline`].
+    pub fn new_separable(stage: ShaderStage, source: &str) -> Result<Self> {
+        fn link_separable<const K: u32>(source: &str) -> Result<Program> {
+            let shader = Shader::<K>::new(source)?;
+            Program::new().separable().with_shader(shader.id).link()
+        }
+
+        match stage {
+            ShaderStage::Vertex => link_separable::<{ gl::VERTEX_SHADER }>(source),
+            ShaderStage::Fragment => link_separable::<{ gl::FRAGMENT_SHADER }>(source),
+            ShaderStage::Geometry => link_separable::<{ gl::GEOMETRY_SHADER }>(source),
+            ShaderStage::TessControl => link_separable::<{ gl::TESS_CONTROL_SHADER }>(source),
+            ShaderStage::TessEvaluation => link_separable::<{ gl::TESS_EVALUATION_SHADER }>(source),
+        }
+        .with_context(|| format!("Cannot create separable {:?} program", stage))
+    }
+
+    /// Load a program from a binary previously returned by [`Self::get_binary`]. Drivers reject
+    /// binaries produced by other drivers or driver versions, in which case this returns an error
+    /// and the program should be compiled from source instead.
+    pub fn from_binary(format: GLenum, data: &[u8]) -> Result<Self> {
+        eyre::ensure!(
+            gl::ProgramBinary::is_loaded(),
+            "Program binaries require OpenGL 4.1 or GL_ARB_get_program_binary"
+        );
+        let id = unsafe { gl::CreateProgram() };
+        // Dropping the program on error deletes it
+        let program = Program {
+            id: ProgramId::new(id).unwrap(),
+            __non_send: PhantomData,
+            __status: Linked,
+        };
+        let is_success = unsafe {
+            gl::ProgramBinary(id, format, data.as_ptr() as *const _, data.len() as _);
+            let mut success = 0;
+            gl::GetProgramiv(id, gl::LINK_STATUS, &mut success);
+            success == gl::TRUE as _
+        };
+        tracing::trace!("glProgramBinary({}) -> success: {}", id, is_success);
+        eyre::ensure!(
+            is_success,
+            "Program binary was rejected by the driver, recompile from source"
+        );
+        Ok(program)
+    }
+
+    /// Retrieve the compiled binary of this program, along with its driver-specific format, to be
+    /// cached and loaded back with [`Self::from_binary`]. The program must have been marked
+    /// `retrievable` before linking.
+    pub fn get_binary(&self) -> Result<(GLenum, Vec<u8>)> {
+        eyre::ensure!(
+            gl::GetProgramBinary::is_loaded(),
+            "Program binaries require OpenGL 4.1 or GL_ARB_get_program_binary"
+        );
+        let id = self.id.get();
+        let mut length = 0;
+        unsafe {
+            gl::GetProgramiv(id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        eyre::ensure!(length > 0, "Program {} has no retrievable binary", self.id);
+        let mut data = vec![0u8; length as usize];
+        let mut format = 0;
+        let mut written = 0;
+        gl_error_guard(|| unsafe {
+            gl::GetProgramBinary(
+                id,
+                length,
+                &mut written,
+                &mut format,
+                data.as_mut_ptr() as *mut _,
+            );
+        })?;
+        data.truncate(written as _);
+        Ok((format, data))
+    }
+
+    /// Create and link a program from SPIR-V binaries, given as `(stage, binary, entry point)`
+    /// tuples. Errors if SPIR-V shaders are not supported by the driver.
+    pub fn from_spirv(stages: &[(ShaderStage, &[u8], &str)]) -> Result<Self> {
+        let mut shaders = Vec::with_capacity(stages.len());
+        let result = stages.iter().try_for_each(|&(stage, binary, entry_point)| {
+            let id = create_spirv_shader(stage as _, binary, entry_point, &[])
+                .with_context(|| format!("Cannot load {:?} SPIR-V shader", stage))?;
+            shaders.push(id);
+            Ok::<_, eyre::Report>(())
+        });
+        let program = result.and_then(|_| {
+            let program = Program::new();
+            for &id in &shaders {
+                tracing::trace!("glAttachShader({}, {})", program.id.get(), id);
+                unsafe { gl::AttachShader(program.id.get(), id) };
+            }
+            program.link()
+        });
+        for id in shaders {
+            tracing::trace!("glDeleteShader({})", id);
+            unsafe { gl::DeleteShader(id) };
+        }
+        program
+    }
+
     /// Load sources and create program from paths to a vertex, optional fragment and optional geometry shaders.
     pub fn from_sources<'vs, 'fs, 'gs>(
         vertex_shader: &'vs str,
@@ -439,6 +768,31 @@ impl Program<Linked> {
         Self::from_sources(&vertex, fragment.as_deref(), geometry.as_deref())
     }
 
+    /// Recompile and relink this program from the given shader sources. The new program replaces
+    /// this one only if it links successfully; otherwise this program is left intact and the
+    /// compilation or link error is returned.
+    ///
+    /// Uniform locations and block indices retrieved from the previous program are invalidated and
+    /// need to be queried again.
+    pub fn reload(
+        &mut self,
+        vertex: &Path,
+        fragment: Option<&Path>,
+        geometry: Option<&Path>,
+    ) -> Result<()> {
+        let program = Self::load(vertex, fragment, geometry)
+            .with_context(|| format!("Cannot reload program {}", self.id))?;
+        tracing::debug!("Reloaded program {} as {}", self.id, program.id);
+        // The previous program is deleted when dropped
+        drop(std::mem::replace(self, program));
+        Ok(())
+    }
+
+    /// Label this program for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        crate::debug::label_object(gl::PROGRAM, self.id.get(), label);
+    }
+
     pub fn num_uniforms(&self) -> usize {
         let mut num_uniforms = 0;
         unsafe {
@@ -479,6 +833,20 @@ impl Program<Linked> {
         }
     }
 
+    /// Looks up a uniform that the program is expected to use, erroring out when it is missing
+    /// (either misspelled or optimized away by the driver). Prefer this over [`Self::uniform`] for
+    /// uniforms that must be set for the program to render correctly.
+    pub fn require_uniform(&self, name: &str) -> Result<UniformLocation> {
+        let location = self.uniform(name);
+        eyre::ensure!(
+            location.is_used(),
+            "Uniform {:?} not found in program {}",
+            name,
+            self.id
+        );
+        Ok(location)
+    }
+
     pub fn uniform_block(&self, name: &str) -> UniformBlockIndex {
         let block_index = unsafe {
             let name = CString::new(name).unwrap();
@@ -499,6 +867,86 @@ impl Program<Linked> {
         }
     }
 
+    /// Size in bytes of the given uniform block, to allocate its backing buffer.
+    pub fn uniform_block_size(&self, block: UniformBlockIndex) -> Result<usize> {
+        self.ensure_block(block)?;
+        let mut size = 0;
+        gl_error_guard(|| unsafe {
+            gl::GetActiveUniformBlockiv(
+                self.id.get(),
+                block.block_index,
+                gl::UNIFORM_BLOCK_DATA_SIZE,
+                &mut size,
+            );
+        })?;
+        Ok(size as _)
+    }
+
+    /// Active members of the given uniform block, sorted by their byte offset within the block.
+    pub fn uniform_block_members(
+        &self,
+        block: UniformBlockIndex,
+    ) -> Result<Vec<UniformBlockMember>> {
+        self.ensure_block(block)?;
+        let id = self.id.get();
+        let indices = gl_error_guard(|| unsafe {
+            let mut count = 0;
+            gl::GetActiveUniformBlockiv(
+                id,
+                block.block_index,
+                gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS,
+                &mut count,
+            );
+            let mut indices = vec![0; count as usize];
+            gl::GetActiveUniformBlockiv(
+                id,
+                block.block_index,
+                gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES,
+                indices.as_mut_ptr(),
+            );
+            indices.into_iter().map(|i| i as GLuint).collect::<Vec<_>>()
+        })?;
+        let query = |pname| {
+            let mut values = vec![0; indices.len()];
+            unsafe {
+                gl::GetActiveUniformsiv(
+                    id,
+                    indices.len() as _,
+                    indices.as_ptr(),
+                    pname,
+                    values.as_mut_ptr(),
+                );
+            }
+            values
+        };
+        let offsets = query(gl::UNIFORM_OFFSET);
+        let name_lengths = query(gl::UNIFORM_NAME_LENGTH);
+        let mut members = indices
+            .iter()
+            .zip(offsets)
+            .zip(name_lengths)
+            .map(|((&index, offset), name_length)| UniformBlockMember {
+                name: gl_string(Some(name_length as _), |len, len_ptr, ptr| unsafe {
+                    gl::GetActiveUniformName(id, index, len as _, len_ptr, ptr)
+                }),
+                offset: offset as _,
+            })
+            .collect::<Vec<_>>();
+        members.sort_by_key(|member| member.offset);
+        Ok(members)
+    }
+
+    fn ensure_block(&self, block: UniformBlockIndex) -> Result<()> {
+        eyre::ensure!(
+            block.program == self.id,
+            "Uniform block is for program {}, not program {}",
+            block.program,
+            self.id
+        );
+        eyre::ensure!(block.is_used(), "Uniform block does not exist");
+        Ok(())
+    }
+
     pub fn num_attributes(&self) -> usize {
         let mut ret = 0;
         unsafe {
@@ -518,9 +966,74 @@ impl Program<Linked> {
             let name = CString::new(name).unwrap();
             gl::GetAttribLocation(self.id.get(), name.as_ptr())
         })?;
-        eyre::ensure!(attr > 0, "Attribute does not exist");
+        // Location 0 is valid, -1 means the attribute does not exist
+        eyre::ensure!(attr >= 0, "Attribute {:?} does not exist", name);
+        // Active attribute indices and locations are not the same, look up by location instead
+        self.get_attributes()
+            .find(|desc| desc.location == attr)
+            .ok_or_else(|| eyre::eyre!("Attribute {:?} does not exist", name))
+    }
 
-        Ok(AttributeDesc::for_attribute(self.id, attr as _))
+    /// Check that the vertex attributes of `V`, as set up in vertex arrays, match the active
+    /// attributes of this program by location, component count and component type.
+    pub fn validate_attributes<V: VertexAttributes>(&self) -> Result<()> {
+        let expected = V::attributes();
+        let actual = self
+            .get_attributes()
+            .filter(|attr| !attr.name.starts_with("gl_"))
+            .collect::<Vec<_>>();
+        eyre::ensure!(
+            expected.len() == actual.len(),
+            "Vertex type {} has {} attributes but program {} declares {}",
+            std::any::type_name::<V>(),
+            expected.len(),
+            self.id,
+            actual.len()
+        );
+        // Matrix attributes take one location per column
+        let locations = expected
+            .iter()
+            .flat_map(|desc| (0..desc.columns).map(move |_| desc))
+            .collect::<Vec<_>>();
+        for attr in actual {
+            let desc = usize::try_from(attr.location)
+                .ok()
+                .and_then(|location| locations.get(location).copied());
+            let Some(desc) = desc else {
+                eyre::bail!(
+                    "Attribute {:?} of program {} at location {} is not provided by vertex type {}",
+                    attr.name,
+                    self.id,
+                    attr.location,
+                    std::any::type_name::<V>()
+                );
+            };
+            let Some((component_type, num_components)) = attribute_components(attr.raw_type) else {
+                // Matrices and other non-vector types aren't checked
+                continue;
+            };
+            // Non-normalized integer attributes are passed as integers with glVertexAttribIPointer,
+            // and read as garbage by float inputs
+            let type_matches = match component_type {
+                gl::DOUBLE => desc.raw_type == gl::DOUBLE,
+                gl::INT | gl::UNSIGNED_INT => desc.is_integer(),
+                _ => !desc.is_integer(),
+            };
+            eyre::ensure!(
+                desc.num_components == num_components && type_matches,
+                "Attribute {:?} of program {} at location {} does not match vertex type {} \
+                 ({} components of type {:#x}, expected {} components of type {:#x})",
+                attr.name,
+                self.id,
+                attr.location,
+                std::any::type_name::<V>(),
+                desc.num_components,
+                desc.raw_type,
+                num_components,
+                component_type
+            );
+        }
+        Ok(())
     }
 
     pub fn set_uniform<T: Uniform>(&self, location: UniformLocation, value: T) -> Result<()> {
@@ -538,11 +1051,149 @@ impl Program<Linked> {
                 self.with_binding(|| unsafe { value.write_uniform(desc.uniform_location as _) })
             })
         } else {
-            tracing::debug!("Uniform location does not exist, skipping");
+            hot_log!(debug!("Uniform location does not exist, skipping"));
+            Ok(())
+        }
+    }
+
+    /// Write an array of uniforms in a single call, starting at the location of its first element.
+    pub fn set_uniform_array<T: Uniform>(
+        &self,
+        location: UniformLocation,
+        values: &[T],
+    ) -> Result<()> {
+        if let Some(desc) = &location.desc {
+            gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    T::write_uniform_array(values, desc.uniform_location as _)
+                })
+            })
+        } else {
+            hot_log!(debug!("Uniform location does not exist, skipping"));
             Ok(())
         }
     }
 
+    /// Write an uniform, or an array of uniforms, from raw bytes. The number of values written is
+    /// derived from the length of the byte slice, which must contain tightly packed values of the
+    /// given kind. This is the escape hatch for uniforms whose type is only known at runtime.
+    pub fn set_uniform_raw(
+        &self,
+        location: UniformLocation,
+        bytes: &[u8],
+        kind: UniformKind,
+    ) -> Result<()> {
+        let value_size = kind.num_components() * 4;
+        let count = bytes.len() / value_size;
+        eyre::ensure!(
+            count > 0 && count * value_size == bytes.len(),
+            "Byte length {} is not a multiple of the size of {:?} ({} bytes)",
+            bytes.len(),
+            kind,
+            value_size
+        );
+        let Some(desc) = &location.desc else {
+            hot_log!(debug!("Uniform location does not exist, skipping"));
+            return Ok(());
+        };
+        // Copy into a 4-byte aligned buffer, as OpenGL reads the data as floats or integers
+        let mut data = vec![0u32; bytes.len() / 4];
+        bytemuck::cast_slice_mut(&mut data).copy_from_slice(bytes);
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                kind.write_raw(desc.uniform_location as _, count as _, &data)
+            })
+        })
+    }
+
+    /// Read back the float components of the uniform at the given location into `out`, which
+    /// must hold at least as many components as the uniform has.
+    pub fn get_uniform_f32(&self, location: UniformLocation, out: &mut [f32]) -> Result<()> {
+        let bounded = gl::GetnUniformfv::is_loaded()
+            .then_some(gl::GetnUniformfv as BoundedUniformGetter<GLfloat>);
+        self.read_uniform(location, out, bounded, gl::GetUniformfv)
+    }
+
+    /// Read back the signed integer components of the uniform at the given location into `out`.
+    /// This also works for sampler and boolean uniforms.
+    pub fn get_uniform_i32(&self, location: UniformLocation, out: &mut [i32]) -> Result<()> {
+        let bounded = gl::GetnUniformiv::is_loaded()
+            .then_some(gl::GetnUniformiv as BoundedUniformGetter<GLint>);
+        self.read_uniform(location, out, bounded, gl::GetUniformiv)
+    }
+
+    /// Read back the unsigned integer components of the uniform at the given location into `out`.
+    pub fn get_uniform_u32(&self, location: UniformLocation, out: &mut [u32]) -> Result<()> {
+        let bounded = gl::GetnUniformuiv::is_loaded()
+            .then_some(gl::GetnUniformuiv as BoundedUniformGetter<GLuint>);
+        self.read_uniform(location, out, bounded, gl::GetUniformuiv)
+    }
+
+    /// Read back `count` float components of the uniform at the given location.
+    pub fn get_uniform_f32_vec(&self, location: UniformLocation, count: usize) -> Result<Vec<f32>> {
+        let mut out = vec![0.; count];
+        self.get_uniform_f32(location, &mut out)?;
+        Ok(out)
+    }
+
+    /// Read back `count` signed integer components of the uniform at the given location.
+    pub fn get_uniform_i32_vec(&self, location: UniformLocation, count: usize) -> Result<Vec<i32>> {
+        let mut out = vec![0; count];
+        self.get_uniform_i32(location, &mut out)?;
+        Ok(out)
+    }
+
+    /// Read back `count` unsigned integer components of the uniform at the given location.
+    pub fn get_uniform_u32_vec(&self, location: UniformLocation, count: usize) -> Result<Vec<u32>> {
+        let mut out = vec![0; count];
+        self.get_uniform_u32(location, &mut out)?;
+        Ok(out)
+    }
+
+    /// Read a uniform with the robust `glGetnUniform*v` getter when available (OpenGL 4.5 or
+    /// `KHR_robustness`), falling back to `glGetUniform*v` otherwise.
+    fn read_uniform<T: Copy + Default>(
+        &self,
+        location: UniformLocation,
+        out: &mut [T],
+        bounded: Option<BoundedUniformGetter<T>>,
+        unbounded: unsafe fn(GLuint, GLint, *mut T),
+    ) -> Result<()> {
+        let desc = self.readable_uniform(&location)?;
+        let program = self.id.get();
+        let location = desc.uniform_location as GLint;
+        if let Some(get) = bounded {
+            // The buffer size lets the driver error out instead of writing past the end of `out`
+            return gl_error_guard(|| unsafe {
+                get(
+                    program,
+                    location,
+                    std::mem::size_of_val(out) as _,
+                    out.as_mut_ptr(),
+                );
+            });
+        }
+        // Without a buffer size, read into storage large enough for any single uniform value
+        let mut scratch = [T::default(); MAX_UNIFORM_COMPONENTS];
+        gl_error_guard(|| unsafe { unbounded(program, location, scratch.as_mut_ptr()) })?;
+        let len = out.len().min(scratch.len());
+        out[..len].copy_from_slice(&scratch[..len]);
+        Ok(())
+    }
+
+    fn readable_uniform<'l>(&self, location: &'l UniformLocation) -> Result<&'l UniformDesc> {
+        eyre::ensure!(
+            location.is_in_program(self),
+            "Uniform location is for program {}, cannot read it from program {}",
+            location.program,
+            self.id
+        );
+        location
+            .desc
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("Uniform location does not exist in program {}", self.id))
+    }
+
     pub fn bind_block<T>(
         &self,
         buf: &BufferSlice<T, { gl::UNIFORM_BUFFER }>,
@@ -577,6 +1228,12 @@ impl Program<Linked> {
     }
 }
 
+/// `glGetnUniform*v` signature, taking the size in bytes of the output buffer.
+type BoundedUniformGetter<T> = unsafe fn(GLuint, GLint, GLsizei, *mut T);
+
+/// Largest number of components returned for a single uniform value, that of a 4x4 matrix.
+const MAX_UNIFORM_COMPONENTS: usize = 16;
+
 #[derive(Debug, Clone, Copy)]
 pub struct UniformDesc {
     program: ProgramId,
@@ -647,10 +1304,131 @@ pub fn current_program() -> Option<ProgramId> {
     })
 }
 
+/// Shader stages used in draw calls, either a single [`Program`] or a [`ProgramPipeline`] made of
+/// separable programs.
+pub trait ShaderPipeline<'a>: Resource<'a> + Sized {
+    /// Run the closure with these shader stages in use.
+    fn with_stages<T>(&'a self, func: impl FnOnce() -> T) -> T {
+        self.with_binding(func)
+    }
+}
+
+impl<'a> ShaderPipeline<'a> for Program {}
+
+bitflags! {
+    /// Shader stages to take from a separable program when adding it to a [`ProgramPipeline`].
+    pub struct ProgramStages: u32 {
+        const VERTEX = gl::VERTEX_SHADER_BIT;
+        const FRAGMENT = gl::FRAGMENT_SHADER_BIT;
+        const GEOMETRY = gl::GEOMETRY_SHADER_BIT;
+        const TESS_CONTROL = gl::TESS_CONTROL_SHADER_BIT;
+        const TESS_EVALUATION = gl::TESS_EVALUATION_SHADER_BIT;
+        const ALL = gl::ALL_SHADER_BITS;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ProgramPipelineId(NonZeroU32);
+
+impl fmt::Display for ProgramPipelineId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.get())
+    }
+}
+
+impl ProgramPipelineId {
+    pub fn new(id: GLuint) -> Option<Self> {
+        NonZeroU32::new(id).map(Self)
+    }
+}
+
+impl std::ops::Deref for ProgramPipelineId {
+    type Target = NonZeroU32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Program pipeline, combining stages of separable programs without relinking them.
+#[derive(Debug)]
+pub struct ProgramPipeline {
+    __non_send: PhantomData<*mut ()>,
+    pub id: ProgramPipelineId,
+}
+
+impl Drop for ProgramPipeline {
+    fn drop(&mut self) {
+        tracing::trace!("glDeleteProgramPipelines({})", self.id.get());
+        unsafe {
+            gl::DeleteProgramPipelines(1, &self.id.get());
+        }
+    }
+}
+
+#[allow(clippy::new_without_default)]
+impl ProgramPipeline {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenProgramPipelines(1, &mut id);
+        }
+        Self {
+            __non_send: PhantomData,
+            id: ProgramPipelineId::new(id).unwrap(),
+        }
+    }
+
+    /// Use the given stages of a separable program in this pipeline.
+    pub fn use_stages(&self, stages: ProgramStages, program: &Program) -> Result<()> {
+        tracing::trace!(
+            "glUseProgramStages({}, {:?}, {})",
+            self.id,
+            stages,
+            program.id
+        );
+        gl_error_guard(|| unsafe {
+            gl::UseProgramStages(self.id.get(), stages.bits, program.id.get());
+        })
+    }
+}
+
+impl<'a> Resource<'a> for ProgramPipeline {
+    type Id = ProgramPipelineId;
+
+    fn id(&self) -> Self::Id {
+        self.id
+    }
+
+    fn current() -> Option<Self::Id> {
+        let mut id = 0;
+        unsafe { gl::GetIntegerv(gl::PROGRAM_PIPELINE_BINDING, &mut id) }
+        Self::Id::new(id as _)
+    }
+
+    fn bind(&self) {
+        unsafe { gl::BindProgramPipeline(self.id.get()) }
+    }
+
+    fn unbind(&self) {
+        unsafe { gl::BindProgramPipeline(0) }
+    }
+}
+
+impl<'a> ShaderPipeline<'a> for ProgramPipeline {
+    fn with_stages<T>(&'a self, func: impl FnOnce() -> T) -> T {
+        // A program in use takes precedence over the bound pipeline
+        unsafe { gl::UseProgram(0) };
+        self.with_binding(func)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AttributeDesc {
     pub program: ProgramId,
     pub index: u32,
+    pub location: i32,
     pub name: Cow<'static, str>,
     pub gl_size: i32,
     raw_type: GLenum,
@@ -675,12 +1453,40 @@ impl AttributeDesc {
                 str_ptr,
             )
         });
+        let location = unsafe {
+            let name = CString::new(name.as_ref()).unwrap();
+            gl::GetAttribLocation(id.get(), name.as_ptr())
+        };
         Self {
             program: id,
             index: attr,
+            location,
             name,
             gl_size,
             raw_type,
         }
     }
 }
+
+/// Component type and count of a GLSL attribute type, as returned by `glGetActiveAttrib`.
+fn attribute_components(raw_type: GLenum) -> Option<(GLenum, usize)> {
+    Some(match raw_type {
+        gl::FLOAT => (gl::FLOAT, 1),
+        gl::FLOAT_VEC2 => (gl::FLOAT, 2),
+        gl::FLOAT_VEC3 => (gl::FLOAT, 3),
+        gl::FLOAT_VEC4 => (gl::FLOAT, 4),
+        gl::DOUBLE => (gl::DOUBLE, 1),
+        gl::DOUBLE_VEC2 => (gl::DOUBLE, 2),
+        gl::DOUBLE_VEC3 => (gl::DOUBLE, 3),
+        gl::DOUBLE_VEC4 => (gl::DOUBLE, 4),
+        gl::INT => (gl::INT, 1),
+        gl::INT_VEC2 => (gl::INT, 2),
+        gl::INT_VEC3 => (gl::INT, 3),
+        gl::INT_VEC4 => (gl::INT, 4),
+        gl::UNSIGNED_INT => (gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC2 => (gl::UNSIGNED_INT, 2),
+        gl::UNSIGNED_INT_VEC3 => (gl::UNSIGNED_INT, 3),
+        gl::UNSIGNED_INT_VEC4 => (gl::UNSIGNED_INT, 4),
+        _ => return None,
+    })
+}
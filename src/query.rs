@@ -0,0 +1,59 @@
+//! Typed wrappers around `glGetIntegerv`/`glGetFloatv`, and named helpers for the commonly needed
+//! implementation limits.
+
+use gl::types::GLenum;
+
+/// Query a single integer value of the OpenGL state.
+pub fn get_i32(pname: GLenum) -> i32 {
+    get_i32v::<1>(pname)[0]
+}
+
+/// Query an integer vector of the OpenGL state, such as `GL_VIEWPORT` or
+/// `GL_MAX_VIEWPORT_DIMS`. `N` needs to be at least the number of values returned for `pname`.
+pub fn get_i32v<const N: usize>(pname: GLenum) -> [i32; N] {
+    let mut values = [0; N];
+    unsafe {
+        gl::GetIntegerv(pname, values.as_mut_ptr());
+    }
+    values
+}
+
+/// Query a single floating-point value of the OpenGL state.
+pub fn get_f32(pname: GLenum) -> f32 {
+    get_f32v::<1>(pname)[0]
+}
+
+/// Query a floating-point vector of the OpenGL state, such as `GL_ALIASED_LINE_WIDTH_RANGE`.
+/// `N` needs to be at least the number of values returned for `pname`.
+pub fn get_f32v<const N: usize>(pname: GLenum) -> [f32; N] {
+    let mut values = [0.0; N];
+    unsafe {
+        gl::GetFloatv(pname, values.as_mut_ptr());
+    }
+    values
+}
+
+/// Largest width or height of a 1D or 2D texture.
+pub fn max_texture_size() -> u32 {
+    get_i32(gl::MAX_TEXTURE_SIZE) as _
+}
+
+/// Maximum number of samples of multisampled textures and framebuffers.
+pub fn max_samples() -> u32 {
+    get_i32(gl::MAX_SAMPLES) as _
+}
+
+/// Maximum number of vertex attribute locations.
+pub fn max_vertex_attribs() -> u32 {
+    get_i32(gl::MAX_VERTEX_ATTRIBS) as _
+}
+
+/// Required alignment in bytes of offsets into uniform buffers bound to an indexed binding.
+pub fn uniform_buffer_offset_alignment() -> u32 {
+    get_i32(gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT) as _
+}
+
+/// Maximum number of texture units that can be used at once across all shader stages.
+pub fn max_combined_texture_image_units() -> u32 {
+    get_i32(gl::MAX_COMBINED_TEXTURE_IMAGE_UNITS) as _
+}
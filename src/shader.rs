@@ -1,8 +1,15 @@
 use std::fmt::Formatter;
 use std::marker::PhantomData;
-use std::{ffi::CString, fmt, num::NonZeroU32, path::Path};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::{
+    ffi::{c_void, CString},
+    fmt,
+    num::NonZeroU32,
+    path::Path,
+};
 
 use eyre::{Context, Result};
+use gl::types::{GLchar, GLenum, GLuint};
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 
@@ -11,6 +18,8 @@ use crate::utils::gl_string;
 pub type VertexShader = Shader<{ gl::VERTEX_SHADER }>;
 pub type FragmentShader = Shader<{ gl::FRAGMENT_SHADER }>;
 pub type GeometryShader = Shader<{ gl::GEOMETRY_SHADER }>;
+pub type TessControlShader = Shader<{ gl::TESS_CONTROL_SHADER }>;
+pub type TessEvaluationShader = Shader<{ gl::TESS_EVALUATION_SHADER }>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 #[repr(u32)]
@@ -20,6 +29,8 @@ pub enum ShaderStage {
     Vertex = gl::VERTEX_SHADER,
     Fragment = gl::FRAGMENT_SHADER,
     Geometry = gl::GEOMETRY_SHADER,
+    TessControl = gl::TESS_CONTROL_SHADER,
+    TessEvaluation = gl::TESS_EVALUATION_SHADER,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -135,9 +146,178 @@ impl<const K: u32> Shader<K> {
         }
     }
 
+    /// Create a shader from the provided source, expanding `#include "path"` lines with the
+    /// contents returned by the resolver. Includes are expanded recursively, and `#line`
+    /// directives are inserted so that compiler errors point to the right line. Each included file
+    /// gets its own source string number, logged at the debug level.
+    pub fn new_with_includes(
+        source: &str,
+        resolver: impl Fn(&str) -> Result<String>,
+    ) -> Result<Self> {
+        let mut expanded = String::with_capacity(source.len());
+        let mut files = vec!["<root>".to_string()];
+        expand_includes(source, 0, &resolver, &mut files, &mut vec![], &mut expanded)?;
+        for (ix, file) in files.iter().enumerate() {
+            tracing::debug!("Shader source string {}: {}", ix, file);
+        }
+        Self::new(&expanded)
+    }
+
+    /// Create a shader from the provided source, with the given preprocessor defines inserted
+    /// right after the `#version` directive (or at the very beginning if there is none). Line
+    /// numbers in compiler errors match the original source.
+    pub fn new_with_defines(source: &str, defines: &[(&str, Option<&str>)]) -> Result<Self> {
+        let version_line = source
+            .lines()
+            .position(|line| line.trim_start().starts_with("#version"));
+        let (header, body) = match version_line {
+            Some(ix) => {
+                let split = source
+                    .split_inclusive('\n')
+                    .take(ix + 1)
+                    .map(str::len)
+                    .sum::<usize>();
+                source.split_at(split)
+            }
+            None => ("", source),
+        };
+        let mut expanded = String::with_capacity(source.len());
+        expanded.push_str(header);
+        if !header.is_empty() && !header.ends_with('\n') {
+            expanded.push('\n');
+        }
+        for (name, value) in defines {
+            expanded.push_str(&format!("#define {} {}\n", name, value.unwrap_or("")));
+        }
+        let next_line = version_line.map_or(1, |ix| ix + 2);
+        expanded.push_str(&format!("#line {}\n", next_line));
+        expanded.push_str(body);
+        Self::new(&expanded)
+    }
+
+    /// Create a shader from a SPIR-V binary, specialized at the given entry point with
+    /// `(constant id, value)` pairs for its specialization constants. Requires OpenGL 4.6 or
+    /// `GL_ARB_gl_spirv`.
+    pub fn from_spirv(binary: &[u8], entry: &str, constants: &[(u32, u32)]) -> Result<Self> {
+        let id = create_spirv_shader(K, binary, entry, constants)?;
+        Ok(Self {
+            __non_send: PhantomData,
+            id: ShaderId::new(id).unwrap(),
+        })
+    }
+
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
         let source = std::fs::read_to_string(path).context("Cannot read shader source")?;
         Self::new(&source).context(format!("Loading {}", path.display()))
     }
 }
+
+const SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+
+type SpecializeShaderFn =
+    unsafe extern "system" fn(GLuint, *const GLchar, GLuint, *const GLuint, *const GLuint);
+
+/// `glSpecializeShader` (or `glSpecializeShaderARB`), which is OpenGL 4.6 and therefore isn't part
+/// of the bindings of the `gl` crate.
+static SPECIALIZE_SHADER: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Load the shader functions not provided by the `gl` crate.
+pub(crate) fn load_extensions_with(loader: &mut impl FnMut(&'static str) -> *const c_void) {
+    let mut ptr = loader("glSpecializeShader");
+    if ptr.is_null() {
+        ptr = loader("glSpecializeShaderARB");
+    }
+    SPECIALIZE_SHADER.store(ptr as *mut _, Ordering::Relaxed);
+}
+
+/// Create a raw shader object of the given stage from a SPIR-V binary, specialized at the given
+/// entry point with `(constant id, value)` pairs. Requires OpenGL 4.6 or `GL_ARB_gl_spirv`.
+pub(crate) fn create_spirv_shader(
+    stage: u32,
+    binary: &[u8],
+    entry_point: &str,
+    constants: &[(u32, u32)],
+) -> Result<u32> {
+    let specialize = SPECIALIZE_SHADER.load(Ordering::Relaxed);
+    eyre::ensure!(
+        !specialize.is_null(),
+        "SPIR-V shaders are not supported (requires OpenGL 4.6 or GL_ARB_gl_spirv)"
+    );
+    let id = unsafe { gl::CreateShader(stage) };
+    tracing::trace!("glCreateShader({:?}) -> {}", stage, id);
+    let (indices, values): (Vec<_>, Vec<_>) = constants.iter().copied().unzip();
+    let success = unsafe {
+        let entry_point = CString::new(entry_point).context("Invalid entry point name")?;
+        gl::ShaderBinary(
+            1,
+            &id,
+            SHADER_BINARY_FORMAT_SPIR_V,
+            binary.as_ptr() as *const _,
+            binary.len() as _,
+        );
+        let specialize = std::mem::transmute::<*mut c_void, SpecializeShaderFn>(specialize);
+        specialize(
+            id,
+            entry_point.as_ptr(),
+            constants.len() as _,
+            indices.as_ptr(),
+            values.as_ptr(),
+        );
+        let mut success = 0;
+        gl::GetShaderiv(id, gl::COMPILE_STATUS, &mut success);
+        success == 1
+    };
+    if !success {
+        let error = unsafe {
+            let mut length = 0;
+            gl::GetShaderiv(id, gl::INFO_LOG_LENGTH, &mut length);
+            let error = gl_string(Some(length as _), |len, len_ptr, ptr| {
+                gl::GetShaderInfoLog(id, len as _, len_ptr, ptr)
+            });
+            gl::DeleteShader(id);
+            error
+        };
+        eyre::bail!(error);
+    }
+    Ok(id)
+}
+
+fn expand_includes(
+    source: &str,
+    source_ix: usize,
+    resolver: &impl Fn(&str) -> Result<String>,
+    files: &mut Vec<String>,
+    stack: &mut Vec<String>,
+    out: &mut String,
+) -> Result<()> {
+    for (line_ix, line) in source.lines().enumerate() {
+        let path = line
+            .trim()
+            .strip_prefix("#include")
+            .map(|rest| rest.trim())
+            .and_then(|rest| rest.strip_prefix('"')?.strip_suffix('"'));
+        let Some(path) = path else {
+            out.push_str(line);
+            out.push('\n');
+            continue;
+        };
+        eyre::ensure!(
+            !stack.iter().any(|p| p == path),
+            "Recursive include of {:?} (included from {})",
+            path,
+            stack.join(" -> ")
+        );
+        let included =
+            resolver(path).with_context(|| format!("Cannot resolve include {:?}", path))?;
+        let included_ix = files.len();
+        files.push(path.to_string());
+        stack.push(path.to_string());
+        out.push_str(&format!("#line 1 {}\n", included_ix));
+        expand_includes(&included, included_ix, resolver, files, stack, out)?;
+        stack.pop();
+        // Resume numbering on the line following the include directive
+        out.push_str(&format!("#line {} {}\n", line_ix + 2, source_ix));
+    }
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use std::{marker::PhantomData, time::Duration};
+
+use gl::types::GLsync;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenceStatus {
+    /// The GPU has executed all commands issued before the fence.
+    Signaled,
+    /// The timeout expired before the fence was signaled.
+    TimedOut,
+    /// Waiting on the fence failed, i.e. because the context was lost.
+    Failed,
+}
+
+/// Synchronization point between the CPU and the GPU, signaled once the GPU has executed all the
+/// commands issued before the fence was created.
+#[derive(Debug)]
+pub struct Fence {
+    __non_send: PhantomData<*mut ()>,
+    sync: GLsync,
+}
+
+impl Drop for Fence {
+    fn drop(&mut self) {
+        tracing::trace!("glDeleteSync({:?})", self.sync);
+        unsafe {
+            gl::DeleteSync(self.sync);
+        }
+    }
+}
+
+#[allow(clippy::new_without_default)]
+impl Fence {
+    /// Insert a fence into the command stream.
+    pub fn new() -> Self {
+        let sync = unsafe { gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0) };
+        tracing::trace!(
+            "glFenceSync(GL_SYNC_GPU_COMMANDS_COMPLETE, 0) -> {:?}",
+            sync
+        );
+        Self {
+            __non_send: PhantomData,
+            sync,
+        }
+    }
+
+    /// Block until the fence is signaled or the timeout expires. Pending commands are flushed so
+    /// that the fence is guaranteed to be signaled eventually.
+    pub fn wait(&self, timeout: Duration) -> FenceStatus {
+        let timeout = u64::try_from(timeout.as_nanos()).unwrap_or(u64::MAX);
+        let result = unsafe { gl::ClientWaitSync(self.sync, gl::SYNC_FLUSH_COMMANDS_BIT, timeout) };
+        match result {
+            gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => FenceStatus::Signaled,
+            gl::TIMEOUT_EXPIRED => FenceStatus::TimedOut,
+            _ => FenceStatus::Failed,
+        }
+    }
+
+    /// Returns true when the fence has been signaled, without blocking.
+    pub fn is_signaled(&self) -> bool {
+        let mut status = 0;
+        unsafe {
+            gl::GetSynciv(
+                self.sync,
+                gl::SYNC_STATUS,
+                1,
+                std::ptr::null_mut(),
+                &mut status,
+            );
+        }
+        status as u32 == gl::SIGNALED
+    }
+}
@@ -1,10 +1,11 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, Ordering};
 use std::{
+    ffi::c_void,
     fmt,
     fmt::Formatter,
     marker::PhantomData,
     num::NonZeroU32,
-    ops::{Deref, DerefMut},
+    ops::{Deref, DerefMut, RangeInclusive},
     path::Path,
 };
 
@@ -22,8 +23,9 @@ use crate::{
         resource::{Resource, ResourceExt},
         GlType,
     },
+    framebuffer::DepthTestFunction,
     program::Uniform,
-    utils::gl_error_guard,
+    utils::{gl_error_guard, row_alignment},
 };
 
 pub trait TextureFormat {
@@ -32,17 +34,20 @@ pub trait TextureFormat {
     const FORMAT: GLenum;
     const TYPE: GLenum;
     const NORMALIZED: bool;
+    /// Whether mipmaps can be generated for textures of this format. Integer and depth formats
+    /// cannot be mipmapped.
+    const MIPMAPPABLE: bool;
 }
 
 #[duplicate(
-rust_t      internal_format     format;
-[u8]        [gl::R8]            [gl::RED];
-[i8]        [gl::R8I]           [gl::RED_INTEGER];
-[u16]       [gl::R16]           [gl::RED];
-[i16]       [gl::R16I]          [gl::RED_INTEGER];
-[u32]       [gl::R32UI]         [gl::RED];
-[i32]       [gl::R32I]          [gl::RED_INTEGER];
-[f32]       [gl::R32F]          [gl::RED];
+rust_t      internal_format     format              mipmappable;
+[u8]        [gl::R8]            [gl::RED]           [true];
+[i8]        [gl::R8I]           [gl::RED_INTEGER]   [false];
+[u16]       [gl::R16]           [gl::RED]           [true];
+[i16]       [gl::R16I]          [gl::RED_INTEGER]   [false];
+[u32]       [gl::R32UI]         [gl::RED]           [false];
+[i32]       [gl::R32I]          [gl::RED_INTEGER]   [false];
+[f32]       [gl::R32F]          [gl::RED]           [true];
 )]
 impl TextureFormat for rust_t {
     type Subpixel = Self;
@@ -50,17 +55,18 @@ impl TextureFormat for rust_t {
     const FORMAT: GLenum = format;
     const TYPE: GLenum = internal_format;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = mipmappable;
 }
 
 #[duplicate(
-rust_t      internal_format     format;
-[u8]        [gl::RG8]           [gl::RG];
-[i8]        [gl::RG8I]          [gl::RG_INTEGER];
-[u16]       [gl::RG16]          [gl::RG];
-[i16]       [gl::RG16I]         [gl::RG_INTEGER];
-[u32]       [gl::RG32UI]        [gl::RG];
-[i32]       [gl::RG32I]         [gl::RG_INTEGER];
-[f32]       [gl::RG32F]         [gl::RG];
+rust_t      internal_format     format              mipmappable;
+[u8]        [gl::RG8]           [gl::RG]            [true];
+[i8]        [gl::RG8I]          [gl::RG_INTEGER]    [false];
+[u16]       [gl::RG16]          [gl::RG]            [true];
+[i16]       [gl::RG16I]         [gl::RG_INTEGER]    [false];
+[u32]       [gl::RG32UI]        [gl::RG]            [false];
+[i32]       [gl::RG32I]         [gl::RG_INTEGER]    [false];
+[f32]       [gl::RG32F]         [gl::RG]            [true];
 )]
 impl TextureFormat for [rust_t; 2] {
     type Subpixel = rust_t;
@@ -68,17 +74,18 @@ impl TextureFormat for [rust_t; 2] {
     const FORMAT: GLenum = format;
     const TYPE: GLenum = internal_format;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = mipmappable;
 }
 
 #[duplicate(
-rust_t      internal_format     format;
-[u8]        [gl::RGB8]          [gl::RGB];
-[i8]        [gl::RGB8I]         [gl::RGB_INTEGER];
-[u16]       [gl::RGB16]         [gl::RGB];
-[i16]       [gl::RGB16I]        [gl::RGB_INTEGER];
-[u32]       [gl::RGB32UI]       [gl::RGB];
-[i32]       [gl::RGB32I]        [gl::RGB_INTEGER];
-[f32]       [gl::RGB32F]        [gl::RGB];
+rust_t      internal_format     format              mipmappable;
+[u8]        [gl::RGB8]          [gl::RGB]           [true];
+[i8]        [gl::RGB8I]         [gl::RGB_INTEGER]   [false];
+[u16]       [gl::RGB16]         [gl::RGB]           [true];
+[i16]       [gl::RGB16I]        [gl::RGB_INTEGER]   [false];
+[u32]       [gl::RGB32UI]       [gl::RGB]           [false];
+[i32]       [gl::RGB32I]        [gl::RGB_INTEGER]   [false];
+[f32]       [gl::RGB32F]        [gl::RGB]           [true];
 )]
 impl TextureFormat for [rust_t; 3] {
     type Subpixel = rust_t;
@@ -86,17 +93,18 @@ impl TextureFormat for [rust_t; 3] {
     const FORMAT: GLenum = format;
     const TYPE: GLenum = internal_format;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = mipmappable;
 }
 
 #[duplicate(
-rust_t      internal_format       format;
-[u8]        [gl::RGBA8]           [gl::RGBA];
-[i8]        [gl::RGBA8I]          [gl::RGBA_INTEGER];
-[u16]       [gl::RGBA16]          [gl::RGBA];
-[i16]       [gl::RGBA16I]         [gl::RGBA_INTEGER];
-[u32]       [gl::RGBA32UI]        [gl::RGBA];
-[i32]       [gl::RGBA32I]         [gl::RGBA_INTEGER];
-[f32]       [gl::RGBA32F]         [gl::RGBA];
+rust_t      internal_format       format              mipmappable;
+[u8]        [gl::RGBA8]           [gl::RGBA]          [true];
+[i8]        [gl::RGBA8I]          [gl::RGBA_INTEGER]  [false];
+[u16]       [gl::RGBA16]          [gl::RGBA]          [true];
+[i16]       [gl::RGBA16I]         [gl::RGBA_INTEGER]  [false];
+[u32]       [gl::RGBA32UI]        [gl::RGBA]          [false];
+[i32]       [gl::RGBA32I]         [gl::RGBA_INTEGER]  [false];
+[f32]       [gl::RGBA32F]         [gl::RGBA]          [true];
 )]
 impl TextureFormat for [rust_t; 4] {
     type Subpixel = rust_t;
@@ -104,6 +112,7 @@ impl TextureFormat for [rust_t; 4] {
     const FORMAT: GLenum = format;
     const TYPE: GLenum = internal_format;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = mipmappable;
 }
 
 pub trait AsTextureFormat {
@@ -152,6 +161,23 @@ impl<F: TextureFormat> TextureFormat for Normalized<F> {
     const FORMAT: GLenum = F::FORMAT;
     const TYPE: GLenum = F::TYPE;
     const NORMALIZED: bool = true;
+    const MIPMAPPABLE: bool = F::MIPMAPPABLE;
+}
+
+/// 8-bit per channel RGBA format with sRGB-encoded color channels and a linear alpha channel.
+/// Sampling decodes the colors to linear values, and rendering with `GL_FRAMEBUFFER_SRGB`
+/// enabled encodes them (see
+/// [`Framebuffer::attach_color_srgb`](crate::framebuffer::Framebuffer::attach_color_srgb)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Srgba8;
+
+impl TextureFormat for Srgba8 {
+    type Subpixel = u8;
+    const COUNT: usize = 4;
+    const FORMAT: GLenum = gl::RGBA;
+    const TYPE: GLenum = gl::SRGB8_ALPHA8;
+    const NORMALIZED: bool = true;
+    const MIPMAPPABLE: bool = true;
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -161,18 +187,81 @@ impl TextureFormat for DepthStencil<f32, ()> {
     type Subpixel = f32;
     const COUNT: usize = 1;
     const FORMAT: GLenum = gl::DEPTH_COMPONENT;
-    const TYPE: GLenum = gl::DEPTH_COMPONENT;
+    const TYPE: GLenum = gl::DEPTH_COMPONENT32F;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = false;
 }
 
 impl TextureFormat for DepthStencil<f32, u8> {
-    type Subpixel = f32;
+    type Subpixel = Depth32FStencil8;
     const COUNT: usize = 1;
-    const FORMAT: GLenum = gl::DEPTH32F_STENCIL8;
-    const TYPE: GLenum = gl::DEPTH_STENCIL;
+    const FORMAT: GLenum = gl::DEPTH_STENCIL;
+    const TYPE: GLenum = gl::DEPTH32F_STENCIL8;
     const NORMALIZED: bool = false;
+    const MIPMAPPABLE: bool = false;
+}
+
+/// Packed texel of a [`DepthStencil<f32, u8>`] texture, as transferred with
+/// `GL_FLOAT_32_UNSIGNED_INT_24_8_REV`. The stencil value is stored in the low 8 bits of `stencil`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(C)]
+pub struct Depth32FStencil8 {
+    pub depth: f32,
+    pub stencil: u32,
+}
+
+unsafe impl Zeroable for Depth32FStencil8 {}
+
+unsafe impl Pod for Depth32FStencil8 {}
+
+impl GlType for Depth32FStencil8 {
+    const GL_TYPE: GLenum = gl::FLOAT_32_UNSIGNED_INT_24_8_REV;
+
+    const NUM_COMPONENTS: usize = 1;
+
+    const NORMALIZED: bool = false;
+
+    const STRIDE: usize = std::mem::size_of::<Self>();
+}
+
+/// 16-bit normalized depth format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depth16;
+
+/// 24-bit normalized depth format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depth24;
+
+/// 32-bit normalized depth format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Depth32;
+
+#[duplicate(
+rust_t      subpixel    internal_format;
+[Depth16]   [u16]       [gl::DEPTH_COMPONENT16];
+[Depth24]   [u32]       [gl::DEPTH_COMPONENT24];
+[Depth32]   [u32]       [gl::DEPTH_COMPONENT32];
+)]
+impl TextureFormat for rust_t {
+    type Subpixel = subpixel;
+    const COUNT: usize = 1;
+    const FORMAT: GLenum = gl::DEPTH_COMPONENT;
+    const TYPE: GLenum = internal_format;
+    const NORMALIZED: bool = true;
+    const MIPMAPPABLE: bool = false;
 }
 
+/// Texture formats which can be attached to the depth slot of a framebuffer.
+pub trait DepthFormat: TextureFormat {}
+
+impl<D, S> DepthFormat for DepthStencil<D, S> where Self: TextureFormat {}
+
+impl DepthFormat for Depth16 {}
+
+impl DepthFormat for Depth24 {}
+
+impl DepthFormat for Depth32 {}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TextureId {
     id: NonZeroU32,
@@ -258,16 +347,30 @@ impl TextureTarget {
     }
 
     pub fn binding_const(&self) -> GLenum {
-        use Dimension::*;
-        match (self.dim, self.is_multisample()) {
-            (D1, _) => gl::TEXTURE_BINDING_1D,
-            (D1Array, _) => gl::TEXTURE_BINDING_1D_ARRAY,
-            (D2, false) => gl::TEXTURE_BINDING_2D,
-            (D2, true) => gl::TEXTURE_BINDING_2D_MULTISAMPLE_ARRAY,
-            (D2Array, false) => gl::TEXTURE_BINDING_2D_ARRAY,
-            (D2Array, true) => gl::TEXTURE_BINDING_2D_MULTISAMPLE_ARRAY,
-            (D3, _) => gl::TEXTURE_BINDING_3D,
-        }
+        binding_const(self.dim, self.is_multisample())
+    }
+}
+
+/// Number of levels in the mip chain of a texture whose largest side is `max_dim`, starting at its
+/// `GL_TEXTURE_BASE_LEVEL` and capped by its `GL_TEXTURE_MAX_LEVEL`. Always at least 1.
+fn mip_chain_len(max_dim: u32, base_level: i32, max_level: i32) -> usize {
+    let full_chain = (u32::BITS - max_dim.leading_zeros()) as usize;
+    let from_base = full_chain.saturating_sub(base_level.max(0) as usize);
+    let level_range = (max_level - base_level).max(0) as usize + 1;
+    from_base.min(level_range).max(1)
+}
+
+/// Binding query (`GL_TEXTURE_BINDING_*`) of textures with the given dimension and sampling.
+fn binding_const(dim: Dimension, multisample: bool) -> GLenum {
+    use Dimension::*;
+    match (dim, multisample) {
+        (D1, _) => gl::TEXTURE_BINDING_1D,
+        (D1Array, _) => gl::TEXTURE_BINDING_1D_ARRAY,
+        (D2, false) => gl::TEXTURE_BINDING_2D,
+        (D2, true) => gl::TEXTURE_BINDING_2D_MULTISAMPLE,
+        (D2Array, false) => gl::TEXTURE_BINDING_2D_ARRAY,
+        (D2Array, true) => gl::TEXTURE_BINDING_2D_MULTISAMPLE_ARRAY,
+        (D3, _) => gl::TEXTURE_BINDING_3D,
     }
 }
 
@@ -308,8 +411,42 @@ impl Uniform for TextureUnit {
     }
 }
 
-// TODO: Refactor texture implementation into a "generic texture" vs. "Texture2D" specializations
+/// Hands out texture units for textures to be bound to, so that textures used by the same draw
+/// call do not collide on a unit.
 #[derive(Debug)]
+pub struct TextureBinder {
+    next: u32,
+    max_units: u32,
+}
+
+#[allow(clippy::new_without_default)]
+impl TextureBinder {
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            max_units: crate::query::max_combined_texture_image_units(),
+        }
+    }
+
+    /// Reserve the next free texture unit.
+    pub fn next_unit(&mut self) -> Result<u32> {
+        eyre::ensure!(
+            self.next < self.max_units,
+            "All {} texture units are in use",
+            self.max_units
+        );
+        let unit = self.next;
+        self.next += 1;
+        Ok(unit)
+    }
+
+    /// Release all texture units, typically once the draw call using them has been issued.
+    pub fn reset(&mut self) {
+        self.next = 0;
+    }
+}
+
+// TODO: Refactor texture implementation into a "generic texture" vs. "Texture2D" specializations
 pub struct Texture<F> {
     __fmt: PhantomData<*mut F>,
     width: NonZeroU32,
@@ -317,6 +454,89 @@ pub struct Texture<F> {
     depth: NonZeroU32,
     id: TextureId,
     has_mipmaps: AtomicBool,
+    /// Cached `GL_TEXTURE_BASE_LEVEL` and `GL_TEXTURE_MAX_LEVEL`, set with
+    /// [`Texture::level_range`].
+    base_level: AtomicI32,
+    max_level: AtomicI32,
+    /// Number of levels of immutable storage, 0 for mutable storage.
+    immutable_levels: AtomicU32,
+}
+
+impl<F> fmt::Debug for Texture<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Texture")
+            .field("id", &self.id.get())
+            .field("format", &std::any::type_name::<F>())
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("depth", &self.depth)
+            .field("dimension", &self.id.target.dim)
+            .field("samples", &self.id.target.samples)
+            .field("has_mipmaps", &self.has_mipmaps.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+impl<F: TextureFormat> fmt::Display for Texture<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Texture#{} {}x{}", self.id, self.width, self.height)?;
+        if self.depth.get() > 1 {
+            write!(f, "x{}", self.depth)?;
+        }
+        write!(
+            f,
+            " {} {:?}",
+            internal_format_name(F::TYPE),
+            self.id.target.dim
+        )?;
+        if self.is_multisample() {
+            write!(f, " {}x MSAA", self.samples())?;
+        }
+        Ok(())
+    }
+}
+
+/// Human-readable name of a texture internal format.
+fn internal_format_name(format: GLenum) -> &'static str {
+    match format {
+        gl::R8 => "R8",
+        gl::R8I => "R8I",
+        gl::R16 => "R16",
+        gl::R16I => "R16I",
+        gl::R32UI => "R32UI",
+        gl::R32I => "R32I",
+        gl::R32F => "R32F",
+        gl::RG8 => "RG8",
+        gl::RG8I => "RG8I",
+        gl::RG16 => "RG16",
+        gl::RG16I => "RG16I",
+        gl::RG32UI => "RG32UI",
+        gl::RG32I => "RG32I",
+        gl::RG32F => "RG32F",
+        gl::RGB8 => "RGB8",
+        gl::RGB8I => "RGB8I",
+        gl::RGB16 => "RGB16",
+        gl::RGB16I => "RGB16I",
+        gl::RGB32UI => "RGB32UI",
+        gl::RGB32I => "RGB32I",
+        gl::RGB32F => "RGB32F",
+        gl::RGBA8 => "RGBA8",
+        gl::RGBA8I => "RGBA8I",
+        gl::RGBA16 => "RGBA16",
+        gl::RGBA16I => "RGBA16I",
+        gl::RGBA32UI => "RGBA32UI",
+        gl::RGBA32I => "RGBA32I",
+        gl::RGBA32F => "RGBA32F",
+        gl::SRGB8_ALPHA8 => "SRGB8_ALPHA8",
+        gl::DEPTH_COMPONENT => "DEPTH_COMPONENT",
+        gl::DEPTH_COMPONENT16 => "DEPTH_COMPONENT16",
+        gl::DEPTH_COMPONENT24 => "DEPTH_COMPONENT24",
+        gl::DEPTH_COMPONENT32 => "DEPTH_COMPONENT32",
+        gl::DEPTH_COMPONENT32F => "DEPTH_COMPONENT32F",
+        gl::DEPTH_STENCIL => "DEPTH_STENCIL",
+        gl::DEPTH32F_STENCIL8 => "DEPTH32F_STENCIL8",
+        _ => "<unknown format>",
+    }
 }
 
 impl<'a, F: 'a> Resource<'a> for Texture<F> {
@@ -351,15 +571,28 @@ impl<F> Texture<F> {
         dim: Dimension,
         samples: NonZeroU32,
     ) -> Self {
+        let target = TextureTarget { dim, samples };
         let mut id = 0;
-        unsafe { gl::GenTextures(1, &mut id) }
+        unsafe {
+            // DSA functions require the texture object to exist, which `glGenTextures` only does
+            // on first bind
+            if cfg!(feature = "dsa") && gl::CreateTextures::is_loaded() {
+                gl::CreateTextures(target.gl_target(), 1, &mut id);
+            } else {
+                gl::GenTextures(1, &mut id);
+            }
+        }
         Self {
             __fmt: PhantomData,
             width,
             height,
             depth,
             has_mipmaps: AtomicBool::new(false),
-            id: TextureId::new(id, TextureTarget { dim, samples }).unwrap(),
+            // OpenGL defaults
+            base_level: AtomicI32::new(0),
+            max_level: AtomicI32::new(1000),
+            immutable_levels: AtomicU32::new(0),
+            id: TextureId::new(id, target).unwrap(),
         }
     }
 
@@ -389,6 +622,26 @@ impl<F> Texture<F> {
         Ok(TextureUnit(unit))
     }
 
+    /// Binds the texture to the given unit along with the sampler, whose parameters then override
+    /// the sampling parameters set on this texture. Returns the texture unit uniform to write into
+    /// the shader program.
+    pub fn bind_with_sampler(&self, unit: u32, sampler: &Sampler) -> Result<TextureUnit> {
+        let uniform = self.as_uniform(unit)?;
+        gl_error_guard(|| unsafe { gl::BindSampler(unit, sampler.id.get()) })?;
+        Ok(uniform)
+    }
+
+    /// Binds the texture to the next free unit of the binder, and returns the texture unit uniform
+    /// to write into the shader program.
+    pub fn bind_next(&self, binder: &mut TextureBinder) -> Result<TextureUnit> {
+        let unit = binder.next_unit()?;
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+        }
+        self.bind();
+        Ok(TextureUnit(unit))
+    }
+
     pub fn dimension(&self) -> Dimension {
         self.id.target.dim
     }
@@ -401,6 +654,23 @@ impl<F> Texture<F> {
         self.id.target.is_multisample()
     }
 
+    /// Number of samples the driver actually allocated for this texture, which can be lower than
+    /// the number of samples requested at creation.
+    pub fn allocated_samples(&self) -> Result<u32> {
+        let mut samples = 0;
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::GetTexLevelParameteriv(
+                    self.id.target.gl_target(),
+                    0,
+                    gl::TEXTURE_SAMPLES,
+                    &mut samples,
+                );
+            })
+        })?;
+        Ok(samples as _)
+    }
+
     pub fn id(&self) -> TextureId {
         self.id
     }
@@ -428,18 +698,36 @@ impl<F> Texture<F> {
         Ok((width, height))
     }
 
+    /// Number of mipmap levels available in this texture, including the base level.
+    ///
+    /// Textures with immutable storage report the number of levels they were allocated with.
+    /// Otherwise this is the length of the mip chain (`floor(log2(max_dim)) + 1` levels) when
+    /// mipmaps have been generated, starting at the base level and capped by the max level set with
+    /// [`Self::level_range`], and 1 when they haven't.
     pub fn num_mipmaps(&self) -> usize {
-        let n = if self.has_mipmaps.load(Ordering::Relaxed) {
-            f32::log2(self.width.max(self.height).max(self.depth).get() as _).floor() as usize
-        } else {
-            0
-        };
-        1 + n
+        let immutable_levels = self.immutable_levels.load(Ordering::Relaxed);
+        if immutable_levels > 0 {
+            return immutable_levels as usize;
+        }
+        if !self.has_mipmaps.load(Ordering::Relaxed) {
+            return 1;
+        }
+        let max_dim = self.width.max(self.height).max(self.depth).get();
+        mip_chain_len(
+            max_dim,
+            self.base_level.load(Ordering::Relaxed),
+            self.max_level.load(Ordering::Relaxed),
+        )
     }
 
     pub(crate) fn raw_id(&self) -> u32 {
         self.id.get()
     }
+
+    /// Label this texture for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        crate::debug::label_object(gl::TEXTURE, self.raw_id(), label);
+    }
 }
 
 impl<F: TextureFormat> Texture<F> {
@@ -462,19 +750,26 @@ impl<F: TextureFormat> Texture<F> {
         Ok(this)
     }
 
+    /// Byte size of a tightly packed row of `width` pixels of this format.
+    fn row_bytes(width: u32) -> usize {
+        width as usize * F::COUNT * std::mem::size_of::<F::Subpixel>()
+    }
+
     pub fn read_pixel(&self, pos: UVec2) -> Result<F::Subpixel> {
         let mut data = vec![0u8; F::Subpixel::STRIDE];
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::ReadPixels(
-                    pos.x as _,
-                    pos.y as _,
-                    1,
-                    1,
-                    F::FORMAT,
-                    F::Subpixel::GL_TYPE,
-                    data.as_mut_ptr().cast(),
-                )
+        crate::with_pack_alignment(row_alignment(Self::row_bytes(1)), || {
+            gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    gl::ReadPixels(
+                        pos.x as _,
+                        pos.y as _,
+                        1,
+                        1,
+                        F::FORMAT,
+                        F::Subpixel::GL_TYPE,
+                        data.as_mut_ptr().cast(),
+                    )
+                })
             })
         })?;
         let data = bytemuck::cast_vec(data);
@@ -506,13 +801,38 @@ impl<F: TextureFormat> Texture<F> {
         Self::from_2d_pixels(image.width().try_into()?, image.as_raw())
     }
 
-    // TODO: Support Non-2D textures
+    // TODO: Support array and cubemap textures
     #[tracing::instrument(skip_all)]
     pub fn reserve_memory(&self) -> Result<()> {
         eyre::ensure!(
-            self.id.target.dim == Dimension::D2,
-            "Non-2D texture not supported at the moment"
+            matches!(self.id.target.dim, Dimension::D2 | Dimension::D3),
+            "Only 2D and 3D textures are supported at the moment"
         );
+        if self.id.target.dim == Dimension::D3 {
+            tracing::trace!(
+                "glTexImage3D(GL_TEXTURE_3D, 0, <INTERNAL_FORMAT {:x}>, {}, {}, {}, 0, ..., NULL)",
+                F::TYPE,
+                self.width,
+                self.height,
+                self.depth
+            );
+            return gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    gl::TexImage3D(
+                        gl::TEXTURE_3D,
+                        0,
+                        F::TYPE as _,
+                        self.width.get() as _,
+                        self.height.get() as _,
+                        self.depth.get() as _,
+                        0,
+                        F::FORMAT,
+                        F::Subpixel::GL_TYPE,
+                        std::ptr::null(),
+                    )
+                })
+            });
+        }
         tracing::trace!(
             "glTexImage2D(<target for dimension {:?}>, 0, <INTERNAL_FORMAT {:x}>, {}, {}, 0, ..., NULL)",
             self.id.target.dim,
@@ -522,17 +842,28 @@ impl<F: TextureFormat> Texture<F> {
         );
         gl_error_guard(|| {
             self.with_binding(|| unsafe {
-                gl::TexImage2D(
-                    self.id.target.gl_target(),
-                    0,
-                    F::TYPE as _,
-                    self.width.get() as _,
-                    self.height.get() as _,
-                    0,
-                    F::FORMAT,
-                    F::Subpixel::GL_TYPE,
-                    std::ptr::null(),
-                )
+                if self.is_multisample() {
+                    gl::TexImage2DMultisample(
+                        self.id.target.gl_target(),
+                        self.id.target.samples.get() as _,
+                        F::TYPE as _,
+                        self.width.get() as _,
+                        self.height.get() as _,
+                        gl::TRUE,
+                    )
+                } else {
+                    gl::TexImage2D(
+                        self.id.target.gl_target(),
+                        0,
+                        F::TYPE as _,
+                        self.width.get() as _,
+                        self.height.get() as _,
+                        0,
+                        F::FORMAT,
+                        F::Subpixel::GL_TYPE,
+                        std::ptr::null(),
+                    )
+                }
             })
         })
     }
@@ -551,34 +882,149 @@ impl<F: TextureFormat> Texture<F> {
         );
 
         let bytes: &[u8] = bytemuck::cast_slice(data);
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                use Dimension::*;
-                match (self.id.target.dim, self.id.target.is_multisample()) {
-                    (D2, false) => gl::TexImage2D(
-                        self.id.target.gl_target(),
+        crate::with_unpack_alignment(row_alignment(Self::row_bytes(self.width.get())), || {
+            if self.id.target.dim == Dimension::D2
+                && !self.is_multisample()
+                && self.has_dsa_storage()
+            {
+                tracing::trace!(
+                    "glTextureSubImage2D({}, 0, 0, 0, {}, {}, ..., <bytes ptr>)",
+                    self.id,
+                    self.width,
+                    self.height
+                );
+                gl_error_guard(|| unsafe {
+                    gl::TextureSubImage2D(
+                        self.id.get(),
+                        0,
+                        0,
                         0,
-                        F::TYPE as _,
                         self.width.get() as _,
                         self.height.get() as _,
-                        0,
                         F::FORMAT,
                         F::Subpixel::GL_TYPE,
-                        bytes.as_ptr() as *const _,
-                    ),
-                    (D2, true) => gl::TexImage2DMultisample(
-                        self.id.target.gl_target(),
-                        self.id.target.samples.get() as _,
-                        F::TYPE as _,
-                        self.width.get() as _,
-                        self.height.get() as _,
-                        gl::TRUE,
-                    ),
-                    _ => todo!(),
-                }
-            })
+                        bytes.as_ptr().cast(),
+                    )
+                })?;
+            } else {
+                gl_error_guard(|| {
+                    self.with_binding(|| unsafe {
+                        use Dimension::*;
+                        match (self.id.target.dim, self.id.target.is_multisample()) {
+                            (D2, false) => gl::TexImage2D(
+                                self.id.target.gl_target(),
+                                0,
+                                F::TYPE as _,
+                                self.width.get() as _,
+                                self.height.get() as _,
+                                0,
+                                F::FORMAT,
+                                F::Subpixel::GL_TYPE,
+                                bytes.as_ptr() as *const _,
+                            ),
+                            (D2, true) => gl::TexImage2DMultisample(
+                                self.id.target.gl_target(),
+                                self.id.target.samples.get() as _,
+                                F::TYPE as _,
+                                self.width.get() as _,
+                                self.height.get() as _,
+                                gl::TRUE,
+                            ),
+                            _ => todo!(),
+                        }
+                    })
+                })?;
+            }
+            Ok(())
+        })?;
+        if F::MIPMAPPABLE && !self.is_multisample() && self.uses_mipmap_filter() {
+            self.generate_mipmaps()?;
+        }
+        Ok(())
+    }
+
+    /// Returns true when Direct State Access is available and the base level of this texture
+    /// already has storage of the texture's size, so that it can be updated in place without
+    /// binding it.
+    fn has_dsa_storage(&self) -> bool {
+        if !(cfg!(feature = "dsa") && gl::GetTextureLevelParameteriv::is_loaded()) {
+            return false;
+        }
+        let mut width = 0;
+        let mut height = 0;
+        unsafe {
+            gl::GetTextureLevelParameteriv(self.id.get(), 0, gl::TEXTURE_WIDTH, &mut width);
+            gl::GetTextureLevelParameteriv(self.id.get(), 0, gl::TEXTURE_HEIGHT, &mut height);
+        }
+        width as u32 == self.width.get() && height as u32 == self.height.get()
+    }
+
+    /// Returns true when the minification filter of this texture samples from mipmaps.
+    fn uses_mipmap_filter(&self) -> bool {
+        let mut filter = 0;
+        self.with_binding(|| unsafe {
+            gl::GetTexParameteriv(
+                self.id.target.gl_target(),
+                gl::TEXTURE_MIN_FILTER,
+                &mut filter,
+            )
+        });
+        !matches!(filter as GLenum, gl::NEAREST | gl::LINEAR)
+    }
+
+    /// Upload data in horizontal strips of `rows_per_chunk` rows, calling `progress` with the
+    /// uploaded fraction after each strip. Storage is allocated once upfront, which lets callers
+    /// spread the upload of very large textures over several frames.
+    pub fn set_data_chunked(
+        &self,
+        data: &[F::Subpixel],
+        rows_per_chunk: u32,
+        mut progress: impl FnMut(f32),
+    ) -> Result<()> {
+        eyre::ensure!(
+            self.id.target.dim == Dimension::D2 && !self.is_multisample(),
+            "Chunked uploads are only supported on non-multisampled 2D textures"
+        );
+        eyre::ensure!(
+            rows_per_chunk > 0,
+            "Chunks need to contain at least one row"
+        );
+        let row_len = self.width.get() as usize * F::COUNT;
+        let height = self.height.get();
+        eyre::ensure!(
+            data.len() == row_len * height as usize,
+            "Data length has to match the extents of the texture"
+        );
+
+        self.reserve_memory()?;
+        crate::with_unpack_alignment(row_alignment(Self::row_bytes(self.width.get())), || {
+            for y in (0..height).step_by(rows_per_chunk as usize) {
+                let rows = rows_per_chunk.min(height - y);
+                let start = y as usize * row_len;
+                let chunk = &data[start..start + rows as usize * row_len];
+                let bytes: &[u8] = bytemuck::cast_slice(chunk);
+                gl_error_guard(|| {
+                    self.with_binding(|| unsafe {
+                        gl::TexSubImage2D(
+                            self.id.target.gl_target(),
+                            0,
+                            0,
+                            y as _,
+                            self.width.get() as _,
+                            rows as _,
+                            F::FORMAT,
+                            F::Subpixel::GL_TYPE,
+                            bytes.as_ptr().cast(),
+                        )
+                    })
+                })?;
+                progress((y + rows) as f32 / height as f32);
+            }
+            Ok(())
         })?;
-        self.generate_mipmaps()?;
+        if F::MIPMAPPABLE && self.uses_mipmap_filter() {
+            self.generate_mipmaps()?;
+        }
         Ok(())
     }
 
@@ -594,11 +1040,11 @@ impl<F: TextureFormat> Texture<F> {
         eyre::ensure!(x >= 0, "Sub data rectangle exceeds texture bounds");
         eyre::ensure!(y >= 0, "Sub data rectangle exceeds texture bounds");
         eyre::ensure!(
-            x + w < self.width.get() as _,
+            x + w <= self.width.get() as _,
             "Sub data rectangle exceeds texture bounds"
         );
         eyre::ensure!(
-            y + h < self.height.get() as _,
+            y + h <= self.height.get() as _,
             "Sub data rectangle exceeds texture bounds"
         );
         eyre::ensure!(
@@ -607,27 +1053,34 @@ impl<F: TextureFormat> Texture<F> {
         );
 
         let bytes: &[u8] = bytemuck::cast_slice(data);
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                match (self.id.target.dim, self.id.target.is_multisample()) {
-                    (Dimension::D2, false) => gl::TexSubImage2D(
-                        self.id.target.gl_target(),
-                        level as _,
-                        x,
-                        y,
-                        w,
-                        h,
-                        F::FORMAT,
-                        F::Subpixel::GL_TYPE,
-                        bytes.as_ptr().cast(),
-                    ),
-                    _ => todo!(),
-                }
+        crate::with_unpack_alignment(row_alignment(Self::row_bytes(w as _)), || {
+            gl_error_guard(|| {
+                self.with_binding(|| unsafe {
+                    match (self.id.target.dim, self.id.target.is_multisample()) {
+                        (Dimension::D2, false) => gl::TexSubImage2D(
+                            self.id.target.gl_target(),
+                            level as _,
+                            x,
+                            y,
+                            w,
+                            h,
+                            F::FORMAT,
+                            F::Subpixel::GL_TYPE,
+                            bytes.as_ptr().cast(),
+                        ),
+                        _ => todo!(),
+                    }
+                })
             })
         })
     }
 
     pub fn generate_mipmaps(&self) -> Result<()> {
+        eyre::ensure!(
+            F::MIPMAPPABLE,
+            "Cannot generate mipmaps for texture format {}",
+            internal_format_name(F::TYPE)
+        );
         gl_error_guard(|| {
             self.with_binding(|| unsafe {
                 gl::GenerateMipmap(self.id.target.gl_target());
@@ -654,40 +1107,159 @@ impl<F: TextureFormat> Texture<F> {
     }
 
     pub fn wrap_s(&self, wrap: TextureWrap) -> Result<()> {
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::TexParameteri(self.id.target.gl_target(), gl::TEXTURE_WRAP_S, wrap as _);
-            })
-        })
+        self.set_parameter(gl::TEXTURE_WRAP_S, wrap as _)
     }
 
     pub fn wrap_t(&self, wrap: TextureWrap) -> Result<()> {
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::TexParameteri(self.id.target.gl_target(), gl::TEXTURE_WRAP_T, wrap as _);
-            })
-        })
+        self.set_parameter(gl::TEXTURE_WRAP_T, wrap as _)
     }
 
     pub fn wrap_r(&self, wrap: TextureWrap) -> Result<()> {
-        gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::TexParameteri(self.id.target.gl_target(), gl::TEXTURE_WRAP_R, wrap as _);
-            })
-        })
+        self.set_parameter(gl::TEXTURE_WRAP_R, wrap as _)
     }
 
     pub fn filter_min(&self, param: SampleMode) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_MIN_FILTER, param as _)
+    }
+
+    pub fn filter_min_mipmap(&self, mipmap: SampleMode, texture: SampleMode) -> Result<()> {
+        use SampleMode::*;
+        let param = match (mipmap, texture) {
+            (Linear, Linear) => gl::LINEAR_MIPMAP_LINEAR,
+            (Nearest, Nearest) => gl::NEAREST_MIPMAP_NEAREST,
+            (Nearest, Linear) => gl::NEAREST_MIPMAP_LINEAR,
+            (Linear, Nearest) => gl::LINEAR_MIPMAP_NEAREST,
+        };
+        self.set_parameter(gl::TEXTURE_MIN_FILTER, param as _)
+    }
+
+    pub fn filter_mag(&self, mode: SampleMode) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_MAG_FILTER, mode as _)
+    }
+
+    /// Set the depth comparison function used when sampling this texture. Passing `None` resets
+    /// the compare mode to `GL_NONE`, so that sampling returns the raw depth values.
+    pub fn compare_mode(&self, func: Option<DepthTestFunction>) -> Result<()> {
+        match func {
+            Some(func) => {
+                self.set_parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as _)?;
+                self.set_parameter(gl::TEXTURE_COMPARE_FUNC, func as _)
+            }
+            None => self.set_parameter(gl::TEXTURE_COMPARE_MODE, gl::NONE as _),
+        }
+    }
+
+    /// Restrict the mipmap levels used when sampling this texture, by setting its
+    /// `GL_TEXTURE_BASE_LEVEL` and `GL_TEXTURE_MAX_LEVEL`.
+    pub fn level_range(&self, levels: RangeInclusive<i32>) -> Result<()> {
+        let (base, max) = levels.into_inner();
+        eyre::ensure!(
+            0 <= base && base <= max,
+            "Invalid mipmap level range {}..={}",
+            base,
+            max
+        );
+        self.set_parameter(gl::TEXTURE_BASE_LEVEL, base)?;
+        self.set_parameter(gl::TEXTURE_MAX_LEVEL, max)?;
+        self.base_level.store(base, Ordering::Relaxed);
+        self.max_level.store(max, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Set an integer texture parameter, without binding the texture when Direct State Access is
+    /// available.
+    fn set_parameter(&self, pname: GLenum, value: GLint) -> Result<()> {
+        if cfg!(feature = "dsa") && gl::TextureParameteri::is_loaded() {
+            return gl_error_guard(|| unsafe {
+                gl::TextureParameteri(self.id.get(), pname, value);
+            });
+        }
         gl_error_guard(|| {
             self.with_binding(|| unsafe {
-                gl::TexParameteri(
-                    self.id.target.gl_target(),
-                    gl::TEXTURE_MIN_FILTER,
-                    param as _,
-                );
+                gl::TexParameteri(self.id.target.gl_target(), pname, value);
             })
         })
     }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct SamplerId(NonZeroU32);
+
+impl fmt::Display for SamplerId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.get())
+    }
+}
+
+impl Deref for SamplerId {
+    type Target = NonZeroU32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl SamplerId {
+    pub fn new(id: u32) -> Option<Self> {
+        Some(Self(NonZeroU32::new(id)?))
+    }
+}
+
+/// Sampling parameters kept separately from texture data, so that the same texture can be sampled
+/// in different ways. Bind with [`Texture::bind_with_sampler`]; while bound to a unit, the sampler
+/// parameters take precedence over the ones set on the texture.
+#[derive(Debug)]
+pub struct Sampler {
+    __non_send: PhantomData<*mut ()>,
+    pub id: SamplerId,
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.id.get());
+        }
+    }
+}
+
+#[allow(clippy::new_without_default)]
+impl Sampler {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenSamplers(1, &mut id);
+        }
+        tracing::debug!("Create sampler {}", id);
+        Self {
+            __non_send: PhantomData,
+            id: SamplerId::new(id).unwrap(),
+        }
+    }
+
+    /// Unbind any sampler from the given texture unit, so that it uses the texture's own sampling
+    /// parameters again.
+    pub fn unbind_unit(unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, 0);
+        }
+    }
+
+    pub fn wrap_s(&self, wrap: TextureWrap) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_WRAP_S, wrap as _)
+    }
+
+    pub fn wrap_t(&self, wrap: TextureWrap) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_WRAP_T, wrap as _)
+    }
+
+    pub fn wrap_r(&self, wrap: TextureWrap) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_WRAP_R, wrap as _)
+    }
+
+    pub fn filter_min(&self, param: SampleMode) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_MIN_FILTER, param as _)
+    }
 
     pub fn filter_min_mipmap(&self, mipmap: SampleMode, texture: SampleMode) -> Result<()> {
         use SampleMode::*;
@@ -697,25 +1269,216 @@ impl<F: TextureFormat> Texture<F> {
             (Nearest, Linear) => gl::NEAREST_MIPMAP_LINEAR,
             (Linear, Nearest) => gl::LINEAR_MIPMAP_NEAREST,
         };
+        self.set_parameter(gl::TEXTURE_MIN_FILTER, param as _)
+    }
+
+    pub fn filter_mag(&self, mode: SampleMode) -> Result<()> {
+        self.set_parameter(gl::TEXTURE_MAG_FILTER, mode as _)
+    }
+
+    /// Set the maximum degree of anisotropic filtering, clamped by the driver to
+    /// `GL_MAX_TEXTURE_MAX_ANISOTROPY`. A value of 1 disables anisotropic filtering.
+    pub fn anisotropy(&self, value: f32) -> Result<()> {
+        eyre::ensure!(value >= 1.0, "Anisotropy must be at least 1");
+        gl_error_guard(|| unsafe {
+            gl::SamplerParameterf(self.id.get(), TEXTURE_MAX_ANISOTROPY, value);
+        })
+    }
+
+    /// Set the depth comparison function used when sampling through this sampler. Passing `None`
+    /// resets the compare mode to `GL_NONE`, so that sampling returns the raw depth values.
+    pub fn compare_mode(&self, func: Option<DepthTestFunction>) -> Result<()> {
+        match func {
+            Some(func) => {
+                self.set_parameter(gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as _)?;
+                self.set_parameter(gl::TEXTURE_COMPARE_FUNC, func as _)
+            }
+            None => self.set_parameter(gl::TEXTURE_COMPARE_MODE, gl::NONE as _),
+        }
+    }
+
+    /// Label this sampler for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        crate::debug::label_object(gl::SAMPLER, self.id.get(), label);
+    }
+
+    fn set_parameter(&self, pname: GLenum, value: GLint) -> Result<()> {
+        gl_error_guard(|| unsafe {
+            gl::SamplerParameteri(self.id.get(), pname, value);
+        })
+    }
+}
+
+/// `GL_TEXTURE_MAX_ANISOTROPY`, core in OpenGL 4.6 and with the same value in
+/// `GL_EXT_texture_filter_anisotropic`.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+
+const TEXTURE_SPARSE_ARB: GLenum = 0x91A6;
+const VIRTUAL_PAGE_SIZE_X_ARB: GLenum = 0x9195;
+const VIRTUAL_PAGE_SIZE_Y_ARB: GLenum = 0x9196;
+const VIRTUAL_PAGE_SIZE_Z_ARB: GLenum = 0x9197;
+
+type TexPageCommitmentFn = unsafe extern "system" fn(
+    GLenum,
+    GLint,
+    GLint,
+    GLint,
+    GLint,
+    GLsizei,
+    GLsizei,
+    GLsizei,
+    GLboolean,
+);
+
+/// `glTexPageCommitmentARB`, which isn't part of the core profile bindings.
+static TEX_PAGE_COMMITMENT: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Load the texture extension functions not provided by the `gl` crate.
+pub(crate) fn load_extensions_with(loader: &mut impl FnMut(&'static str) -> *const c_void) {
+    let ptr = loader("glTexPageCommitmentARB");
+    TEX_PAGE_COMMITMENT.store(ptr as *mut _, Ordering::Relaxed);
+}
+
+/// Returns true when the `GL_ARB_sparse_texture` extension is available.
+pub fn is_sparse_supported() -> bool {
+    if TEX_PAGE_COMMITMENT.load(Ordering::Relaxed).is_null() {
+        return false;
+    }
+    crate::has_extension("GL_ARB_sparse_texture")
+}
+
+/// 2D texture whose memory is only committed for the regions which are needed, allowing textures
+/// larger than what fits in GPU memory. Requires the `GL_ARB_sparse_texture` extension.
+///
+/// The storage is immutable, so only the parts of [`Texture`] which don't re-specify it are
+/// exposed. Sampling parameters are set with a [`Sampler`].
+#[derive(Debug)]
+pub struct SparseTexture<F> {
+    texture: Texture<F>,
+}
+
+impl<F> SparseTexture<F> {
+    pub fn size(&self) -> (NonZeroU32, NonZeroU32) {
+        (self.texture.width, self.texture.height)
+    }
+
+    pub fn num_mipmaps(&self) -> usize {
+        self.texture.num_mipmaps()
+    }
+
+    /// See [`Texture::as_uniform`].
+    pub fn as_uniform(&self, unit: u32) -> Result<TextureUnit> {
+        self.texture.as_uniform(unit)
+    }
+
+    /// See [`Texture::bind_with_sampler`].
+    pub fn bind_with_sampler(&self, unit: u32, sampler: &Sampler) -> Result<TextureUnit> {
+        self.texture.bind_with_sampler(unit, sampler)
+    }
+
+    /// See [`Texture::bind_next`].
+    pub fn bind_next(&self, binder: &mut TextureBinder) -> Result<TextureUnit> {
+        self.texture.bind_next(binder)
+    }
+
+    /// Label this texture for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        self.texture.set_label(label)
+    }
+}
+
+impl<F: TextureFormat> SparseTexture<F> {
+    /// Create a sparse 2D texture with immutable storage for the given number of mipmap levels.
+    /// No memory is committed until [`commit_region`](Self::commit_region) is called.
+    pub fn new(width: NonZeroU32, height: NonZeroU32, levels: NonZeroU32) -> Result<Self> {
+        eyre::ensure!(
+            is_sparse_supported(),
+            "Sparse textures require the GL_ARB_sparse_texture extension"
+        );
+        let texture = Texture::new(width, height, NonZeroU32::new(1).unwrap(), Dimension::D2);
         gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::TexParameteri(
-                    self.id.target.gl_target(),
-                    gl::TEXTURE_MIN_FILTER,
-                    param as _,
-                )
+            texture.with_binding(|| unsafe {
+                gl::TexParameteri(gl::TEXTURE_2D, TEXTURE_SPARSE_ARB, gl::TRUE as _);
+                gl::TexStorage2D(
+                    gl::TEXTURE_2D,
+                    levels.get() as _,
+                    F::TYPE,
+                    width.get() as _,
+                    height.get() as _,
+                );
             })
-        })
+        })?;
+        texture
+            .has_mipmaps
+            .store(levels.get() > 1, Ordering::Relaxed);
+        texture
+            .immutable_levels
+            .store(levels.get(), Ordering::Relaxed);
+        Ok(Self { texture })
     }
 
-    pub fn filter_mag(&self, mode: SampleMode) -> Result<()> {
+    /// Size of a virtual page of this texture's format. Regions to commit need to be aligned to it.
+    pub fn page_size(&self) -> Result<UVec3> {
+        let mut size = [0; 3];
+        gl_error_guard(|| unsafe {
+            for (pname, value) in [
+                VIRTUAL_PAGE_SIZE_X_ARB,
+                VIRTUAL_PAGE_SIZE_Y_ARB,
+                VIRTUAL_PAGE_SIZE_Z_ARB,
+            ]
+            .into_iter()
+            .zip(size.iter_mut())
+            {
+                gl::GetInternalformativ(gl::TEXTURE_2D, F::TYPE, pname, 1, value);
+            }
+        })?;
+        let [x, y, z] = size;
+        Ok(UVec3::new(x as _, y as _, z as _))
+    }
+
+    /// Get a mipmap level of this texture, i.e. to download it or attach it to a framebuffer.
+    pub fn mipmap(&self, level: usize) -> Result<Mipmap<'_, F>> {
+        self.texture.mipmap(level)
+    }
+
+    /// Upload data into a rectangle of the given mipmap level, whose pages must have been
+    /// committed. See [`Texture::set_sub_data_2d`].
+    pub fn set_sub_data_2d(
+        &self,
+        level: usize,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        data: &[F::Subpixel],
+    ) -> Result<()> {
+        self.texture.set_sub_data_2d(level, x, y, w, h, data)
+    }
+
+    /// Commit or release the memory backing a region of the given mipmap level.
+    pub fn commit_region(
+        &self,
+        level: usize,
+        origin: UVec3,
+        size: UVec3,
+        committed: bool,
+    ) -> Result<()> {
+        let ptr = TEX_PAGE_COMMITMENT.load(Ordering::Relaxed);
+        eyre::ensure!(!ptr.is_null(), "glTexPageCommitmentARB is not loaded");
         gl_error_guard(|| {
-            self.with_binding(|| unsafe {
-                gl::TexParameteri(
-                    self.id.target.gl_target(),
-                    gl::TEXTURE_MAG_FILTER,
-                    mode as _,
-                )
+            self.texture.with_binding(|| unsafe {
+                let commit = std::mem::transmute::<*mut c_void, TexPageCommitmentFn>(ptr);
+                commit(
+                    gl::TEXTURE_2D,
+                    level as _,
+                    origin.x as _,
+                    origin.y as _,
+                    origin.z as _,
+                    size.x as _,
+                    size.y as _,
+                    size.z as _,
+                    if committed { gl::TRUE } else { gl::FALSE },
+                );
             })
         })
     }
@@ -813,30 +1576,157 @@ impl<'a, F: TextureFormat> Mipmap<'a, F> {
         (width, height)
     }
 
+    /// Download the pixels of this mipmap. Slices of 3D textures are laid out one after the other.
     pub fn download(&self) -> Result<Vec<F::Subpixel>> {
         self.texture.bind();
         let (w, h) = self.size();
-        let size = (w.get() * h.get()) as usize * F::COUNT;
-        let mut data = vec![F::Subpixel::zeroed(); size];
-        gl_error_guard(|| unsafe {
-            gl::GetTexImage(
+        let mut depth = 1;
+        unsafe {
+            gl::GetTexLevelParameteriv(
                 self.texture.id.target.gl_target(),
                 self.level as _,
-                F::FORMAT,
-                F::Subpixel::GL_TYPE,
-                // (std::mem::size_of::<F::Subpixel>() * size) as _,
-                data.as_mut_ptr().cast(),
+                gl::TEXTURE_DEPTH,
+                &mut depth,
             );
+        }
+        let size = (w.get() * h.get()) as usize * depth.max(1) as usize * F::COUNT;
+        let mut data = vec![F::Subpixel::zeroed(); size];
+        crate::with_pack_alignment(row_alignment(Texture::<F>::row_bytes(w.get())), || {
+            gl_error_guard(|| unsafe {
+                gl::GetTexImage(
+                    self.texture.id.target.gl_target(),
+                    self.level as _,
+                    F::FORMAT,
+                    F::Subpixel::GL_TYPE,
+                    // (std::mem::size_of::<F::Subpixel>() * size) as _,
+                    data.as_mut_ptr().cast(),
+                );
+            })
         })?;
         Ok(data)
     }
 
+    /// Download this mipmap into an image. The rows are flipped vertically to match the layout of
+    /// the `image` crate, such that this is the inverse of [`Texture::from_image`].
     #[cfg(feature = "img")]
     pub fn download_image<P: image::Pixel<Subpixel = F::Subpixel>>(
         &self,
     ) -> Result<image::ImageBuffer<P, Vec<P::Subpixel>>> {
+        eyre::ensure!(
+            P::CHANNEL_COUNT as usize == F::COUNT,
+            "Cannot download a texture with {} channels into pixels with {} channels",
+            F::COUNT,
+            P::CHANNEL_COUNT
+        );
         let (width, height) = self.size();
         let data = self.download()?;
-        Ok(image::ImageBuffer::from_vec(width.get(), height.get(), data).unwrap())
+        let mut image = image::ImageBuffer::from_vec(width.get(), height.get(), data)
+            .ok_or_else(|| eyre::eyre!("Downloaded data does not match the mipmap size"))?;
+        image::imageops::flip_vertical_in_place(&mut image);
+        Ok(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binding_const_of_single_sampled_textures() {
+        assert_eq!(binding_const(Dimension::D1, false), gl::TEXTURE_BINDING_1D);
+        assert_eq!(
+            binding_const(Dimension::D1Array, false),
+            gl::TEXTURE_BINDING_1D_ARRAY
+        );
+        assert_eq!(binding_const(Dimension::D2, false), gl::TEXTURE_BINDING_2D);
+        assert_eq!(
+            binding_const(Dimension::D2Array, false),
+            gl::TEXTURE_BINDING_2D_ARRAY
+        );
+        assert_eq!(binding_const(Dimension::D3, false), gl::TEXTURE_BINDING_3D);
+    }
+
+    #[test]
+    fn binding_const_of_multisampled_textures() {
+        assert_eq!(
+            binding_const(Dimension::D2, true),
+            gl::TEXTURE_BINDING_2D_MULTISAMPLE
+        );
+        assert_eq!(
+            binding_const(Dimension::D2Array, true),
+            gl::TEXTURE_BINDING_2D_MULTISAMPLE_ARRAY
+        );
+    }
+
+    #[test]
+    fn mip_chain_len_of_power_of_two_sizes() {
+        assert_eq!(mip_chain_len(1, 0, 1000), 1);
+        assert_eq!(mip_chain_len(2, 0, 1000), 2);
+        assert_eq!(mip_chain_len(1024, 0, 1000), 11);
+    }
+
+    #[test]
+    fn mip_chain_len_of_non_power_of_two_sizes() {
+        assert_eq!(mip_chain_len(3, 0, 1000), 2);
+        assert_eq!(mip_chain_len(5, 0, 1000), 3);
+        assert_eq!(mip_chain_len(1000, 0, 1000), 10);
+        assert_eq!(mip_chain_len(1025, 0, 1000), 11);
+    }
+
+    #[test]
+    fn mip_chain_len_is_capped_by_level_range() {
+        assert_eq!(mip_chain_len(1024, 0, 3), 4);
+        assert_eq!(mip_chain_len(1024, 2, 4), 3);
+        assert_eq!(mip_chain_len(1024, 4, 4), 1);
+        assert_eq!(mip_chain_len(1024, 5, 2), 1);
+    }
+
+    /// Texture value built without an OpenGL context, for testing formatting.
+    fn texture<F>(size: [u32; 3], dim: Dimension, samples: u32) -> Texture<F> {
+        let [width, height, depth] = size.map(|x| NonZeroU32::new(x).unwrap());
+        let samples = NonZeroU32::new(samples).unwrap();
+        Texture {
+            __fmt: PhantomData,
+            width,
+            height,
+            depth,
+            id: TextureId::new(7, TextureTarget { dim, samples }).unwrap(),
+            has_mipmaps: AtomicBool::new(false),
+            base_level: AtomicI32::new(0),
+            max_level: AtomicI32::new(1000),
+            immutable_levels: AtomicU32::new(0),
+        }
+    }
+
+    #[test]
+    fn texture_display() {
+        assert_eq!(
+            texture::<[f32; 4]>([64, 32, 1], Dimension::D2, 1).to_string(),
+            "Texture#7 64x32 RGBA32F D2"
+        );
+        assert_eq!(
+            texture::<u8>([4, 4, 8], Dimension::D3, 1).to_string(),
+            "Texture#7 4x4x8 R8 D3"
+        );
+        assert_eq!(
+            texture::<Srgba8>([16, 16, 1], Dimension::D2, 4).to_string(),
+            "Texture#7 16x16 SRGB8_ALPHA8 D2 4x MSAA"
+        );
+    }
+
+    #[test]
+    fn texture_debug() {
+        let debug = format!("{:?}", texture::<[f32; 4]>([64, 32, 1], Dimension::D2, 1));
+        assert!(debug.starts_with("Texture { id: 7, format: \"[f32; 4]\", width: 64, height: 32"));
+        assert!(debug.contains("dimension: D2"));
+        assert!(debug.contains("has_mipmaps: false"));
+    }
+
+    #[test]
+    fn mip_chain_len_starts_at_base_level() {
+        assert_eq!(mip_chain_len(1024, 8, 1000), 3);
+        assert_eq!(mip_chain_len(4, 2, 1000), 1);
+        assert_eq!(mip_chain_len(1000, 3, 1000), 7);
+        assert_eq!(mip_chain_len(4, 5, 1000), 1);
     }
 }
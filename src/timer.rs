@@ -0,0 +1,105 @@
+use std::{fmt, marker::PhantomData, num::NonZeroU32};
+
+use eyre::Result;
+
+use crate::utils::gl_error_guard;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct QueryId(NonZeroU32);
+
+impl fmt::Display for QueryId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.get())
+    }
+}
+
+impl std::ops::Deref for QueryId {
+    type Target = NonZeroU32;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl QueryId {
+    pub fn new(id: u32) -> Option<Self> {
+        Some(Self(NonZeroU32::new(id)?))
+    }
+}
+
+/// GPU timer, measuring the time taken by the GPU to execute the commands issued between
+/// [`TimerQuery::begin`] and [`TimerQuery::end`]. Results are available asynchronously, once the
+/// GPU has caught up.
+#[derive(Debug)]
+pub struct TimerQuery {
+    __non_send: PhantomData<*mut ()>,
+    pub id: QueryId,
+}
+
+impl Drop for TimerQuery {
+    fn drop(&mut self) {
+        tracing::trace!("glDeleteQueries({})", self.id);
+        unsafe {
+            gl::DeleteQueries(1, &self.id.get());
+        }
+    }
+}
+
+#[allow(clippy::new_without_default)]
+impl TimerQuery {
+    pub fn new() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenQueries(1, &mut id);
+        }
+        Self {
+            __non_send: PhantomData,
+            id: QueryId::new(id).unwrap(),
+        }
+    }
+
+    /// Start measuring. Only one timer query can be active at a time.
+    pub fn begin(&self) -> Result<()> {
+        tracing::trace!("glBeginQuery(GL_TIME_ELAPSED, {})", self.id);
+        gl_error_guard(|| unsafe { gl::BeginQuery(gl::TIME_ELAPSED, self.id.get()) })
+    }
+
+    /// Stop measuring.
+    pub fn end(&self) {
+        tracing::trace!("glEndQuery(GL_TIME_ELAPSED)");
+        unsafe { gl::EndQuery(gl::TIME_ELAPSED) }
+    }
+
+    /// Measure the commands issued during the lifetime of the returned guard.
+    pub fn scope(&self) -> Result<TimerScope<'_>> {
+        self.begin()?;
+        Ok(TimerScope { query: self })
+    }
+
+    /// Elapsed GPU time in nanoseconds, or `None` if the result is not available yet.
+    pub fn elapsed_ns(&self) -> Option<u64> {
+        unsafe {
+            let mut available = 0;
+            gl::GetQueryObjectiv(self.id.get(), gl::QUERY_RESULT_AVAILABLE, &mut available);
+            if available == gl::FALSE as _ {
+                return None;
+            }
+            let mut elapsed = 0;
+            gl::GetQueryObjectui64v(self.id.get(), gl::QUERY_RESULT, &mut elapsed);
+            Some(elapsed)
+        }
+    }
+}
+
+/// Guard ending the timer query when dropped.
+#[derive(Debug)]
+pub struct TimerScope<'a> {
+    query: &'a TimerQuery,
+}
+
+impl<'a> Drop for TimerScope<'a> {
+    fn drop(&mut self) {
+        self.query.end();
+    }
+}
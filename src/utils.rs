@@ -12,6 +12,12 @@ use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
 use thiserror::Error;
 
+/// Largest pixel store alignment (`GL_PACK_ALIGNMENT`/`GL_UNPACK_ALIGNMENT`) which tightly packed
+/// rows of the given byte size satisfy.
+pub(crate) fn row_alignment(row_bytes: usize) -> i32 {
+    1 << row_bytes.trailing_zeros().min(3)
+}
+
 /// Helper for converting OpenGL string messages into Rust's String type.
 pub(crate) fn gl_string(
     planned_length: Option<usize>,
@@ -108,3 +114,16 @@ impl<'a, T> GlRef<'a, T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn row_alignment_is_largest_dividing_power_of_two() {
+        assert_eq!(row_alignment(16), 8);
+        assert_eq!(row_alignment(12), 4);
+        assert_eq!(row_alignment(6), 2);
+        assert_eq!(row_alignment(3), 1);
+    }
+}
@@ -1,3 +1,6 @@
+use std::any::TypeId;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::{
     fmt::{self, Formatter},
@@ -23,6 +26,9 @@ pub struct VertexDesc {
     pub raw_type: GLenum,
     pub normalized: bool,
     pub offset: usize,
+    /// Number of consecutive attribute locations taken by this attribute, one per column for
+    /// matrices and 1 otherwise.
+    pub columns: usize,
 }
 
 impl VertexDesc {
@@ -32,6 +38,7 @@ impl VertexDesc {
             raw_type: T::GL_TYPE,
             normalized: T::NORMALIZED,
             offset,
+            columns: T::COLUMNS,
         }
     }
 
@@ -39,6 +46,29 @@ impl VertexDesc {
         self.normalized = true;
         self
     }
+
+    /// Byte offsets of each column of this attribute, each of which takes its own attribute
+    /// location.
+    pub fn column_offsets(&self) -> impl Iterator<Item = usize> {
+        let column_size = self.num_components * component_size(self.raw_type);
+        let offset = self.offset;
+        (0..self.columns).map(move |c| offset + c * column_size)
+    }
+
+    /// Returns true when the attribute is read as an integer in the shader (non-normalized integer
+    /// type), which requires `glVertexAttribIPointer` instead of `glVertexAttribPointer`.
+    pub const fn is_integer(&self) -> bool {
+        !self.normalized
+            && matches!(
+                self.raw_type,
+                gl::BYTE
+                    | gl::UNSIGNED_BYTE
+                    | gl::SHORT
+                    | gl::UNSIGNED_SHORT
+                    | gl::INT
+                    | gl::UNSIGNED_INT
+            )
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -74,13 +104,53 @@ pub enum DrawMode {
     Lines = gl::LINES,
     LineLoop = gl::LINE_LOOP,
     LineStrip = gl::LINE_STRIP,
+    TriangleStrip = gl::TRIANGLE_STRIP,
+    TriangleFan = gl::TRIANGLE_FAN,
+    LinesAdjacency = gl::LINES_ADJACENCY,
+    TriangleStripAdjacency = gl::TRIANGLE_STRIP_ADJACENCY,
+    Patches = gl::PATCHES,
 }
 
+/// Draw command of [`Framebuffer::draw_indirect`](crate::framebuffer::Framebuffer::draw_indirect),
+/// laid out as expected by `glDrawArraysIndirect` in a draw indirect buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct DrawArraysIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first: u32,
+    pub base_instance: u32,
+}
+
+unsafe impl bytemuck::Zeroable for DrawArraysIndirectCommand {}
+
+unsafe impl bytemuck::Pod for DrawArraysIndirectCommand {}
+
+/// Draw command of
+/// [`Framebuffer::draw_elements_indirect`](crate::framebuffer::Framebuffer::draw_elements_indirect),
+/// laid out as expected by `glDrawElementsIndirect` in a draw indirect buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct DrawElementsIndirectCommand {
+    pub count: u32,
+    pub instance_count: u32,
+    pub first_index: u32,
+    pub base_vertex: i32,
+    pub base_instance: u32,
+}
+
+unsafe impl bytemuck::Zeroable for DrawElementsIndirectCommand {}
+
+unsafe impl bytemuck::Pod for DrawElementsIndirectCommand {}
+
 #[derive(Debug)]
 pub struct VertexArray {
     __non_send: PhantomData<*mut ()>,
     id: VaoId,
     pub(crate) element: Option<GLenum>,
+    pub(crate) vertex_count: usize,
+    pub(crate) element_count: usize,
+    next_attribute: u32,
 }
 
 impl<'a> Resource<'a> for VertexArray {
@@ -118,6 +188,9 @@ impl VertexArray {
             __non_send: PhantomData,
             id: VaoId::new(id).unwrap(),
             element: None,
+            vertex_count: 0,
+            element_count: 0,
+            next_attribute: 0,
         }
     }
 }
@@ -133,29 +206,128 @@ impl Drop for VertexArray {
 
 impl VertexArray {
     pub fn set_vertex_attributes<V>(&mut self) -> Result<usize>
+    where
+        V: VertexAttributes,
+    {
+        self.set_vertex_attributes_from::<V>(0)
+    }
+
+    fn set_vertex_attributes_from<V>(&mut self, base: u32) -> Result<usize>
     where
         V: VertexAttributes,
     {
         gl_error_guard(|| {
             self.with_binding(|| {
-                let attr = V::attributes();
-                for (i, el) in attr.iter().enumerate() {
-                    unsafe {
-                        gl::VertexAttribPointer(
-                            i as _,
-                            el.num_components as _,
-                            el.raw_type,
-                            if el.normalized { gl::TRUE } else { gl::FALSE },
-                            std::mem::size_of::<V>() as _,
-                            el.offset as *const _,
-                        );
+                let stride = std::mem::size_of::<V>() as _;
+                let mut location = base;
+                for el in V::attributes() {
+                    for offset in el.column_offsets() {
+                        if el.is_integer() {
+                            unsafe {
+                                gl::VertexAttribIPointer(
+                                    location,
+                                    el.num_components as _,
+                                    el.raw_type,
+                                    stride,
+                                    offset as *const _,
+                                );
+                            }
+                        } else {
+                            unsafe {
+                                gl::VertexAttribPointer(
+                                    location,
+                                    el.num_components as _,
+                                    el.raw_type,
+                                    if el.normalized { gl::TRUE } else { gl::FALSE },
+                                    stride,
+                                    offset as *const _,
+                                );
+                            }
+                        }
+                        location += 1;
                     }
                 }
-                attr.len()
+                (location - base) as usize
             })
         })
     }
 
+    /// Describe the vertex layout of `V` using the separate attribute format API (OpenGL 4.3),
+    /// without binding any buffer. All attributes source their data from binding index 0, which
+    /// can then be (re)bound with [`Self::bind_vertex_buffer`] without redefining the format.
+    pub fn set_attribute_format<V>(&mut self) -> Result<()>
+    where
+        V: VertexAttributes,
+    {
+        eyre::ensure!(
+            gl::VertexAttribFormat::is_loaded(),
+            "Separate vertex attribute formats require OpenGL 4.3 or GL_ARB_vertex_attrib_binding"
+        );
+        let count = gl_error_guard(|| {
+            self.with_binding(|| {
+                let mut location = 0;
+                for el in V::attributes() {
+                    for offset in el.column_offsets() {
+                        unsafe {
+                            if el.is_integer() {
+                                gl::VertexAttribIFormat(
+                                    location,
+                                    el.num_components as _,
+                                    el.raw_type,
+                                    offset as _,
+                                );
+                            } else {
+                                gl::VertexAttribFormat(
+                                    location,
+                                    el.num_components as _,
+                                    el.raw_type,
+                                    if el.normalized { gl::TRUE } else { gl::FALSE },
+                                    offset as _,
+                                );
+                            }
+                            gl::VertexAttribBinding(location, 0);
+                            gl::EnableVertexAttribArray(location);
+                        }
+                        location += 1;
+                    }
+                }
+                location
+            })
+        })?;
+        self.next_attribute = count;
+        Ok(())
+    }
+
+    /// Bind a buffer to the given vertex buffer binding index, with `offset` and `stride` in
+    /// bytes. The layout of the attributes reading from this binding is set separately with
+    /// [`Self::set_attribute_format`].
+    pub fn bind_vertex_buffer<V: 'static>(
+        &mut self,
+        binding: u32,
+        buffer: &ArrayBuffer<V>,
+        offset: usize,
+        stride: usize,
+    ) -> Result<()> {
+        eyre::ensure!(
+            gl::BindVertexBuffer::is_loaded(),
+            "Separate vertex buffer bindings require OpenGL 4.3 or GL_ARB_vertex_attrib_binding"
+        );
+        tracing::trace!(
+            "glBindVertexBuffer({}, {}, {}, {})",
+            binding,
+            buffer.id,
+            offset,
+            stride
+        );
+        gl_error_guard(|| {
+            self.with_binding(|| unsafe {
+                gl::BindVertexBuffer(binding, buffer.id.get(), offset as _, stride as _);
+            })
+        })?;
+        self.vertex_count = buffer.len();
+        Ok(())
+    }
+
     pub fn enable_vertex_attribute(&mut self, index: usize) {
         self.with_binding(|| unsafe {
             gl::EnableVertexAttribArray(index as _);
@@ -183,13 +355,55 @@ impl VertexArray {
             vertex_buffer.unbind();
             Ok(())
         })
-        .and_then(|r| r)
+        .and_then(|r| r)?;
+        self.vertex_count = vertex_buffer.len();
+        self.next_attribute = attribute_locations(V::attributes()) as _;
+        Ok(())
+    }
+
+    /// Binds a per-instance buffer to this vertex array. Its attributes are placed after the ones
+    /// already set by [`Self::with_vertex_buffer`] (and any previous instance buffers), and advance
+    /// once every `divisor` instances.
+    ///
+    /// Calling [`Self::with_vertex_buffer`] again resets the attribute layout, so instance buffers
+    /// must be bound after the vertex buffer.
+    pub fn with_instance_buffer<V>(&mut self, buffer: &ArrayBuffer<V>, divisor: u32) -> Result<()>
+    where
+        V: 'static + VertexAttributes,
+    {
+        let base = self.next_attribute;
+        gl_error_guard(|| {
+            self.bind();
+            buffer.bind();
+            let attrib_count = self.set_vertex_attributes_from::<V>(base)?;
+            // The divisor applies to the bound vertex array, which `set_vertex_attributes_from`
+            // unbinds when done
+            self.with_binding(|| {
+                for i in base..base + attrib_count as u32 {
+                    tracing::trace!("glVertexAttribDivisor({}, {})", i, divisor);
+                    unsafe {
+                        gl::EnableVertexAttribArray(i);
+                        gl::VertexAttribDivisor(i, divisor);
+                    }
+                }
+            });
+            self.unbind();
+            buffer.unbind();
+            Ok(())
+        })
+        .and_then(|r| r)?;
+        self.next_attribute = base + attribute_locations(V::attributes()) as u32;
+        Ok(())
     }
 
     pub fn with_element_buffer<T: GlType>(
         &mut self,
         element_buffer: &ElementBuffer<T>,
     ) -> Result<()> {
+        eyre::ensure!(
+            T::NUM_COMPONENTS == 1 && index_size(T::GL_TYPE).is_some(),
+            "Element buffers must contain u8, u16 or u32 indices"
+        );
         gl_error_guard(|| {
             self.bind();
             element_buffer.bind();
@@ -197,16 +411,207 @@ impl VertexArray {
             element_buffer.unbind();
         })?;
         self.element.replace(T::GL_TYPE);
+        self.element_count = element_buffer.len();
         Ok(())
     }
+
+    /// Label this vertex array for graphics debuggers.
+    pub fn set_label(&self, label: &str) {
+        crate::debug::label_object(gl::VERTEX_ARRAY, self.id.get(), label);
+    }
+
+    /// Type of the indices in the element buffer bound to this vertex array, if any.
+    pub fn index_type(&self) -> Option<GLenum> {
+        self.element
+    }
+
+    /// Returns true when an element buffer is bound to this vertex array.
+    pub fn has_elements(&self) -> bool {
+        self.element.is_some()
+    }
+}
+
+thread_local! {
+    // Leaked rather than dropped by the thread-local destructor, which runs at thread exit, once
+    // the OpenGL context is usually gone or no longer current.
+    static FULLSCREEN_VAO: Cell<Option<&'static VertexArray>> = const { Cell::new(None) };
+    static FULLSCREEN_QUAD_VAO: Cell<Option<&'static VertexArray>> = const { Cell::new(None) };
+}
+
+/// Get the vertex array cached in `cell`, creating it with `init` on first use, or when the cached
+/// one isn't a vertex array of the current context anymore (i.e. after the context was recreated).
+fn cached_vao(
+    cell: &Cell<Option<&'static VertexArray>>,
+    init: impl FnOnce() -> Result<VertexArray>,
+) -> Result<&'static VertexArray> {
+    if let Some(vao) = cell.get() {
+        if unsafe { gl::IsVertexArray(vao.id.get()) } == gl::TRUE {
+            return Ok(vao);
+        }
+    }
+    let vao = &*Box::leak(Box::new(init()?));
+    cell.set(Some(vao));
+    Ok(vao)
+}
+
+/// Shared empty vertex array, for drawing primitives whose vertices are generated in the vertex
+/// shader from `gl_VertexID`. Created on first use on the calling thread, which must have a
+/// current OpenGL context, and recreated if that context changes. Never freed.
+pub(crate) fn fullscreen_vao() -> Result<&'static VertexArray> {
+    FULLSCREEN_VAO.with(|cell| {
+        cached_vao(cell, || {
+            let vao = VertexArray::new();
+            // Names from glGenVertexArrays only become vertex arrays once bound
+            vao.with_binding(|| {});
+            Ok(vao)
+        })
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+struct QuadVertex {
+    position: [f32; 2],
+    uv: [f32; 2],
+}
+
+unsafe impl bytemuck::Zeroable for QuadVertex {}
+
+unsafe impl bytemuck::Pod for QuadVertex {}
+
+static QUAD_ATTRIBUTES: [VertexDesc; 2] = [
+    VertexDesc::from_gl_type::<[f32; 2]>(0),
+    VertexDesc::from_gl_type::<[f32; 2]>(std::mem::size_of::<[f32; 2]>()),
+];
+
+impl VertexAttributes for QuadVertex {
+    fn attributes() -> &'static [VertexDesc] {
+        &QUAD_ATTRIBUTES
+    }
+}
+
+/// Shared vertex array of a quad covering the whole viewport, to be drawn as 4 vertices with
+/// [`DrawMode::TriangleStrip`]. Attribute 0 is the `vec2` position in normalized device
+/// coordinates, and attribute 1 the `vec2` UV, from (0, 0) in the bottom-left corner to (1, 1)
+/// in the top-right one.
+///
+/// Created on first use on the calling thread, which must have a current OpenGL context, and
+/// recreated if that context changes. Never freed.
+pub fn fullscreen_quad_vao() -> Result<&'static VertexArray> {
+    FULLSCREEN_QUAD_VAO.with(|cell| {
+        cached_vao(cell, || {
+            let vertices = [
+                ([-1., -1.], [0., 0.]),
+                ([1., -1.], [1., 0.]),
+                ([-1., 1.], [0., 1.]),
+                ([1., 1.], [1., 1.]),
+            ]
+            .map(|(position, uv)| QuadVertex { position, uv });
+            // The buffer is leaked along with the vertex array which references it
+            let buffer = Box::leak(Box::new(ArrayBuffer::with_data(&vertices)?));
+            let mut vao = VertexArray::new();
+            vao.with_vertex_buffer(buffer)?;
+            Ok(vao)
+        })
+    })
+}
+
+/// Size in bytes of a single component of the given type.
+pub(crate) fn component_size(gl_type: GLenum) -> usize {
+    match gl_type {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT | gl::HALF_FLOAT => 2,
+        gl::DOUBLE => 8,
+        _ => 4,
+    }
+}
+
+/// Number of attribute locations taken by the given attributes.
+pub(crate) fn attribute_locations(attributes: &[VertexDesc]) -> usize {
+    attributes.iter().map(|desc| desc.columns).sum()
+}
+
+/// Size in bytes of a single index of the given type, if it is a valid index type.
+pub(crate) fn index_size(gl_type: GLenum) -> Option<usize> {
+    match gl_type {
+        gl::UNSIGNED_BYTE => Some(1),
+        gl::UNSIGNED_SHORT => Some(2),
+        gl::UNSIGNED_INT => Some(4),
+        _ => None,
+    }
 }
 
 pub trait VertexAttributes: Sized + bytemuck::Pod {
     fn attributes() -> &'static [VertexDesc];
 }
 
+thread_local! {
+    /// Single-attribute descriptions of plain [`GlType`] vertices, leaked once per type so that
+    /// repeated calls to [`VertexAttributes::attributes`] hand back the same slice.
+    static SINGLE_ATTRIBUTES: RefCell<HashMap<TypeId, &'static [VertexDesc]>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Single-attribute description of the given type, taken from (or leaked into) the per-thread
+/// cache.
+fn single_attributes<T: GlType + 'static>() -> &'static [VertexDesc] {
+    SINGLE_ATTRIBUTES.with(|cache| {
+        *cache
+            .borrow_mut()
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| vec![VertexDesc::from_gl_type::<T>(0)].leak())
+    })
+}
+
 impl<T: GlType + bytemuck::Pod> VertexAttributes for T {
     fn attributes() -> &'static [VertexDesc] {
-        vec![VertexDesc::from_gl_type::<T>(0)].leak()
+        single_attributes::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_attributes_are_cached() {
+        let first = single_attributes::<[f32; 3]>();
+        let second = single_attributes::<[f32; 3]>();
+        assert!(std::ptr::eq(first, second));
+        assert!(std::ptr::eq(first, <[f32; 3]>::attributes()));
+    }
+
+    #[test]
+    fn single_attributes_describe_their_type() {
+        assert_eq!(
+            single_attributes::<[f32; 3]>(),
+            [VertexDesc::from_gl_type::<[f32; 3]>(0)]
+        );
+        assert_eq!(
+            single_attributes::<u32>(),
+            [VertexDesc::from_gl_type::<u32>(0)]
+        );
+        assert!(!std::ptr::eq(
+            single_attributes::<u32>(),
+            single_attributes::<[f32; 3]>()
+        ));
+    }
+
+    #[test]
+    fn matrix_attributes_take_one_location_per_column() {
+        use crate::base::MatrixAttribute;
+
+        type Mat4 = MatrixAttribute<4, [f32; 4]>;
+        let attributes = [
+            VertexDesc::from_gl_type::<[f32; 3]>(0),
+            VertexDesc::from_gl_type::<Mat4>(std::mem::size_of::<[f32; 3]>()),
+        ];
+        assert_eq!(attribute_locations(&attributes), 5);
+        let offsets = attributes
+            .iter()
+            .flat_map(|desc| desc.column_offsets())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, [0, 12, 28, 44, 60]);
+        assert!(attributes[1].num_components == 4 && attributes[1].raw_type == gl::FLOAT);
     }
 }
@@ -0,0 +1,1080 @@
+//! Tests running against a real OpenGL context. They run sequentially on the main thread, which
+//! owns the context, and are skipped when no context can be created (i.e. on machines without a
+//! display or a software renderer).
+
+use std::num::NonZeroU32;
+
+use eyre::Result;
+use glutin::{dpi::PhysicalSize, event_loop::EventLoop, Api, ContextBuilder, GlProfile, GlRequest};
+use violette::{
+    buffer::{ArrayBuffer, BufferAccess, DynamicBuffer, ElementBuffer},
+    debug,
+    framebuffer::{
+        draw_fullscreen_quad, Blend, BlendFunction, BlendState, ClearBuffer, ColorEncoding,
+        DepthTestFunction, Framebuffer, FramebufferAttachment, FramebufferStatus,
+        MultisampleFramebuffer,
+    },
+    gl,
+    program::{Program, ProgramPipeline, ProgramStages, UniformKind},
+    query,
+    shader::{FragmentShader, ShaderStage, VertexShader},
+    texture::{
+        self, Depth16, Depth32FStencil8, DepthStencil, Dimension, SampleMode, SparseTexture,
+        Srgba8, Texture, TextureBinder,
+    },
+    vertex::{DrawMode, VertexArray},
+    Cull, CullState, FrontFace,
+};
+
+const VERTEX_SHADER: &str = r#"#version 330 core
+void main() {
+    gl_Position = vec4(0.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+const WHITE_FRAGMENT_SHADER: &str = r#"#version 330 core
+out vec4 color;
+void main() {
+    color = vec4(1.0);
+}
+"#;
+
+/// Vertex shader covering the viewport with a single triangle, for `draw_fullscreen_quad`.
+const FULLSCREEN_VERTEX_SHADER: &str = r#"#version 330 core
+void main() {
+    vec2 uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#;
+
+/// Float color texture of the given size, attached to a new framebuffer which is cleared to
+/// transparent black and set as the viewport.
+fn render_target(width: u32, height: u32) -> Result<(Texture<[f32; 4]>, Framebuffer)> {
+    let texture = Texture::<[f32; 4]>::new(
+        NonZeroU32::new(width).unwrap(),
+        NonZeroU32::new(height).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+        Dimension::D2,
+    );
+    texture.reserve_memory()?;
+    let fbo = Framebuffer::builder()
+        .with_color(0, texture.mipmap(0)?)?
+        .build()?;
+    fbo.clear_color_buffer(0, [0.0; 4])?;
+    Framebuffer::viewport(0, 0, width as _, height as _);
+    Ok((texture, fbo))
+}
+
+fn uniform_vec3_round_trip() -> Result<()> {
+    let program = Program::from_sources(
+        VERTEX_SHADER,
+        r#"#version 330 core
+uniform vec3 u_color;
+out vec4 color;
+void main() {
+    color = vec4(u_color, 1.0);
+}
+"#,
+        None::<&str>,
+    )?;
+    let location = program.require_uniform("u_color")?;
+    program.set_uniform(location, [0.25f32, 0.5, 0.75])?;
+    let value = program.get_uniform_f32_vec(location, 3)?;
+    eyre::ensure!(value == [0.25, 0.5, 0.75], "Read back {:?}", value);
+    Ok(())
+}
+
+fn uniform_raw_round_trip() -> Result<()> {
+    let program = Program::from_sources(
+        VERTEX_SHADER,
+        r#"#version 330 core
+uniform vec3 u_color;
+out vec4 color;
+void main() {
+    color = vec4(u_color, 1.0);
+}
+"#,
+        None::<&str>,
+    )?;
+    let location = program.require_uniform("u_color")?;
+    let bytes: &[u8] = bytemuck::cast_slice(&[0.25f32, 0.5, 0.75]);
+    eyre::ensure!(
+        program
+            .set_uniform_raw(location, &bytes[..8], UniformKind::Vec3)
+            .is_err(),
+        "Truncated value was accepted"
+    );
+    program.set_uniform_raw(location, bytes, UniformKind::Vec3)?;
+    let value = program.get_uniform_f32_vec(location, 3)?;
+    eyre::ensure!(value == [0.25, 0.5, 0.75], "Read back {:?}", value);
+    Ok(())
+}
+
+fn uniform_ivec2_into_slice() -> Result<()> {
+    let program = Program::from_sources(
+        VERTEX_SHADER,
+        r#"#version 330 core
+uniform ivec2 u_offset;
+out vec4 color;
+void main() {
+    color = vec4(vec2(u_offset), 0.0, 1.0);
+}
+"#,
+        None::<&str>,
+    )?;
+    let location = program.require_uniform("u_offset")?;
+    program.set_uniform(location, [-3i32, 7])?;
+    let mut value = [0; 2];
+    program.get_uniform_i32(location, &mut value)?;
+    eyre::ensure!(value == [-3, 7], "Read back {:?}", value);
+    Ok(())
+}
+
+fn buffer_clear_range() -> Result<()> {
+    let mut buffer = ArrayBuffer::with_data(&[0u32; 8])?;
+    buffer.clear_range(2..5, &7)?;
+    let slice = buffer.slice(..);
+    let data = slice.get_all(BufferAccess::MAP_READ)?;
+    eyre::ensure!(*data == [0, 0, 7, 7, 7, 0, 0, 0], "Read back {:?}", &*data);
+    drop(data);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = 5..2;
+    eyre::ensure!(
+        buffer.clear_range(reversed, &7).is_err(),
+        "Reversed range was accepted"
+    );
+    Ok(())
+}
+
+fn fullscreen_quad_uvs() -> Result<()> {
+    let program = Program::from_sources(
+        r#"#version 330 core
+out vec2 v_uv;
+void main() {
+    v_uv = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2);
+    gl_Position = vec4(v_uv * 2.0 - 1.0, 0.0, 1.0);
+}
+"#,
+        r#"#version 330 core
+in vec2 v_uv;
+out vec4 color;
+void main() {
+    color = vec4(v_uv, 0.0, 1.0);
+}
+"#,
+        None::<&str>,
+    )?;
+    let (_texture, fbo) = render_target(2, 2)?;
+    draw_fullscreen_quad(&fbo, &program)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 2])?;
+    // Pixel centers sit a quarter of the way in from the edges, so UVs going from (0, 0) to (1, 1)
+    // across the corners are sampled as 0.25 and 0.75 on the bottom-left and top-right pixels
+    let uv = |pixel: usize| [pixels[pixel * 4], pixels[pixel * 4 + 1]];
+    for (pixel, expected) in [(0, [0.25, 0.25]), (3, [0.75, 0.75])] {
+        let actual = uv(pixel);
+        eyre::ensure!(
+            actual
+                .iter()
+                .zip(expected)
+                .all(|(a, b)| (a - b).abs() < 1e-3),
+            "Pixel {} has UV {:?}, expected {:?}",
+            pixel,
+            actual,
+            expected
+        );
+    }
+    Ok(())
+}
+
+fn odd_width_rgb_round_trip() -> Result<()> {
+    // Rows of 3 RGB texels are 9 bytes long, which the default alignment of 4 would misread
+    let data: Vec<u8> = (0..18).collect();
+    let texture = Texture::<[u8; 3]>::new(
+        NonZeroU32::new(3).unwrap(),
+        NonZeroU32::new(2).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+        Dimension::D2,
+    );
+    texture.set_data(&data)?;
+    let downloaded = texture.mipmap(0)?.download()?;
+    eyre::ensure!(downloaded == data, "Read back {:?}", downloaded);
+    for pname in [gl::UNPACK_ALIGNMENT, gl::PACK_ALIGNMENT] {
+        let alignment = query::get_i32(pname);
+        eyre::ensure!(alignment == 4, "Alignment was left at {}", alignment);
+    }
+    Ok(())
+}
+
+fn instanced_attributes() -> Result<()> {
+    let program = Program::from_sources(
+        r#"#version 330 core
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 offset;
+void main() {
+    gl_Position = vec4(position + offset, 0.0, 1.0);
+}
+"#,
+        WHITE_FRAGMENT_SHADER,
+        None::<&str>,
+    )?;
+    let vertices = ArrayBuffer::with_data(&[[0f32; 2]])?;
+    // One point per instance, on the center of each pixel of a 2x1 target
+    let offsets = ArrayBuffer::with_data(&[[-0.5f32, 0.0], [0.5, 0.0]])?;
+    let mut vao = VertexArray::new();
+    vao.with_vertex_buffer(&vertices)?;
+    vao.with_instance_buffer(&offsets, 1)?;
+    let (_texture, fbo) = render_target(2, 1)?;
+    fbo.draw_instanced_base_instance(&program, &vao, DrawMode::Points, 0..1, 2, 0)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 1])?;
+    eyre::ensure!(pixels == [1.0; 8], "Read back {:?}", pixels);
+    Ok(())
+}
+
+fn separate_vertex_format() -> Result<()> {
+    let program = Program::from_sources(
+        r#"#version 330 core
+layout(location = 0) in vec2 position;
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#,
+        WHITE_FRAGMENT_SHADER,
+        None::<&str>,
+    )?;
+    let left = ArrayBuffer::with_data(&[[-0.5f32, 0.0]])?;
+    let right = ArrayBuffer::with_data(&[[0.5f32, 0.0]])?;
+    let mut vao = VertexArray::new();
+    vao.set_attribute_format::<[f32; 2]>()?;
+    let (_texture, fbo) = render_target(2, 1)?;
+
+    vao.bind_vertex_buffer(0, &left, 0, std::mem::size_of::<[f32; 2]>())?;
+    fbo.draw(&program, &vao, DrawMode::Points, 0..1)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 1])?;
+    eyre::ensure!(
+        pixels == [1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0],
+        "Read back {:?}",
+        pixels
+    );
+
+    // Swapping the buffer keeps the attribute format
+    vao.bind_vertex_buffer(0, &right, 0, std::mem::size_of::<[f32; 2]>())?;
+    fbo.draw(&program, &vao, DrawMode::Points, 0..1)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 1])?;
+    eyre::ensure!(pixels == [1.0; 8], "Read back {:?}", pixels);
+    Ok(())
+}
+
+fn validate_attribute_mismatch() -> Result<()> {
+    let program = Program::from_sources(
+        r#"#version 330 core
+layout(location = 0) in vec3 position;
+void main() {
+    gl_Position = vec4(position, 1.0);
+}
+"#,
+        WHITE_FRAGMENT_SHADER,
+        None::<&str>,
+    )?;
+    program.validate_attributes::<[f32; 3]>()?;
+    eyre::ensure!(
+        program.validate_attributes::<[f32; 2]>().is_err(),
+        "vec2 vertex type was accepted for a vec3 input"
+    );
+    eyre::ensure!(
+        program.validate_attributes::<[i32; 3]>().is_err(),
+        "Integer vertex type was accepted for a float input"
+    );
+    Ok(())
+}
+
+fn program_binary_round_trip() -> Result<()> {
+    let vertex = VertexShader::new(VERTEX_SHADER)?;
+    let fragment = FragmentShader::new(WHITE_FRAGMENT_SHADER)?;
+    let program = Program::new()
+        .with_shader(vertex.id)
+        .with_shader(fragment.id)
+        .retrievable()?
+        .link()?;
+    let (format, binary) = program.get_binary()?;
+    Program::from_binary(format, &binary)?;
+    Ok(())
+}
+
+fn float_clear_of_integer_attachment() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let texture = Texture::<[i32; 4]>::new(size, size, NonZeroU32::new(1).unwrap(), Dimension::D2);
+    texture.reserve_memory()?;
+    let fbo = Framebuffer::builder()
+        .with_color(0, texture.mipmap(0)?)?
+        .build()?;
+    Framebuffer::clear_color([0.0; 4]);
+    let Err(err) = fbo.do_clear(ClearBuffer::COLOR) else {
+        eyre::bail!("Float clear of an integer attachment was accepted");
+    };
+    eyre::ensure!(
+        err.to_string().contains("clear_buffer_i"),
+        "Unexpected error: {}",
+        err
+    );
+    fbo.clear_buffer_i(0, [1, -2, 3, -4])?;
+    let pixels = fbo.read_pixels::<[i32; 4]>(0, [0, 0, 1, 1])?;
+    eyre::ensure!(pixels == [1, -2, 3, -4], "Read back {:?}", pixels);
+    Ok(())
+}
+
+fn persistent_mapping_flush() -> Result<()> {
+    let flags = BufferAccess::MAP_READ | BufferAccess::MAP_WRITE | BufferAccess::PERSISTENT;
+    let buffer = ArrayBuffer::<u32>::with_storage(4, flags)?;
+    let slice = buffer.slice(..);
+    let mut mapping = slice.map_persistent(BufferAccess::MAP_WRITE | BufferAccess::PERSISTENT)?;
+    mapping.write(0, &[1, 2, 3, 4])?;
+    mapping.flush_range(0..4)?;
+    eyre::ensure!(
+        mapping.flush_range(2..5).is_err(),
+        "Out of bounds flush was accepted"
+    );
+    drop(mapping);
+    let data = slice.get_all(BufferAccess::MAP_READ)?;
+    eyre::ensure!(*data == [1, 2, 3, 4], "Read back {:?}", &*data);
+    Ok(())
+}
+
+fn depth16_render_read_back() -> Result<()> {
+    let program = Program::from_sources(
+        FULLSCREEN_VERTEX_SHADER,
+        WHITE_FRAGMENT_SHADER,
+        None::<&str>,
+    )?;
+    let size = NonZeroU32::new(2).unwrap();
+    let one = NonZeroU32::new(1).unwrap();
+    let color = Texture::<[f32; 4]>::new(size, size, one, Dimension::D2);
+    color.reserve_memory()?;
+    let depth = Texture::<Depth16>::new(size, size, one, Dimension::D2);
+    depth.reserve_memory()?;
+    let fbo = Framebuffer::builder()
+        .with_color(0, color.mipmap(0)?)?
+        .with_depth(&depth)?
+        .build()?;
+    Framebuffer::viewport(0, 0, 2, 2);
+    Framebuffer::clear_depth(1.0);
+    fbo.do_clear(ClearBuffer::DEPTH)?;
+    Framebuffer::enable_depth_test(DepthTestFunction::Less);
+    let result = draw_fullscreen_quad(&fbo, &program);
+    Framebuffer::disable_depth_test();
+    result?;
+    // Z = 0 in clip space lands halfway through the default depth range
+    let values = depth.mipmap(0)?.download()?;
+    eyre::ensure!(
+        values.iter().all(|&v| v.abs_diff(u16::MAX / 2) <= 1),
+        "Read back {:?}",
+        values
+    );
+    Ok(())
+}
+
+fn attachment_color_encoding() -> Result<()> {
+    let (_texture, fbo) = render_target(1, 1)?;
+    let encoding = fbo.color_encoding(FramebufferAttachment::Color(0))?;
+    eyre::ensure!(
+        encoding == ColorEncoding::Linear,
+        "Float attachment has {:?} encoding",
+        encoding
+    );
+    // Surfaceless contexts have no default framebuffer to query
+    let backbuffer = Framebuffer::backbuffer();
+    if backbuffer.check_status() == FramebufferStatus::Complete {
+        backbuffer.color_encoding(FramebufferAttachment::Color(0))?;
+    }
+    Ok(())
+}
+
+fn sparse_texture_commit_upload() -> Result<()> {
+    if !texture::is_sparse_supported() {
+        eprintln!("Skipping sparse_texture_commit_upload: GL_ARB_sparse_texture not supported");
+        return Ok(());
+    }
+    let page = SparseTexture::<[f32; 4]>::new(
+        NonZeroU32::new(1).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+    )?
+    .page_size()?;
+    let (width, height) = (
+        NonZeroU32::new(page.x).unwrap(),
+        NonZeroU32::new(page.y).unwrap(),
+    );
+    let texture = SparseTexture::<[f32; 4]>::new(width, height, NonZeroU32::new(1).unwrap())?;
+    texture.commit_region(0, glam::UVec3::ZERO, page, true)?;
+    let data = (0..page.x * page.y * 4)
+        .map(|i| i as f32)
+        .collect::<Vec<_>>();
+    texture.set_sub_data_2d(0, 0, 0, page.x as _, page.y as _, &data)?;
+    let read_back = texture.mipmap(0)?.download()?;
+    eyre::ensure!(read_back == data, "Read back different data");
+    texture.commit_region(0, glam::UVec3::ZERO, page, false)?;
+    Ok(())
+}
+
+fn assert_no_error_drains_errors() -> Result<()> {
+    debug::assert_no_error("before")?;
+    unsafe {
+        gl::Enable(0xFFFF);
+        gl::LineWidth(-1.0);
+    }
+    let Err(err) = debug::assert_no_error("invalid calls") else {
+        eyre::bail!("Errors were not reported");
+    };
+    let message = err.to_string();
+    eyre::ensure!(
+        message.contains("invalid calls") && message.contains("enum value is not valid"),
+        "Unexpected error: {}",
+        message
+    );
+    // Implementations may keep a single error flag (Mesa only records the first error), so the
+    // second error is only reported where the driver tracks both. Either way, none is left behind.
+    debug::assert_no_error("after")
+}
+
+fn blend_state_round_trip() -> Result<()> {
+    let saved = BlendState::current()?;
+    let changed = BlendState {
+        enabled: !saved.enabled,
+        source_rgb: Blend::SrcAlpha,
+        target_rgb: Blend::OneMinusSrcAlpha,
+        source_alpha: Blend::One,
+        target_alpha: Blend::Zero,
+        equation_rgb: BlendFunction::RevSubtract,
+        equation_alpha: BlendFunction::Max,
+        color: [0.25, 0.5, 0.75, 1.0],
+    };
+    changed.apply();
+    let current = BlendState::current();
+    saved.apply();
+    let current = current?;
+    eyre::ensure!(
+        current == changed,
+        "Applied {:?}, got {:?}",
+        changed,
+        current
+    );
+    let restored = BlendState::current()?;
+    eyre::ensure!(
+        restored == saved,
+        "Restored {:?}, got {:?}",
+        saved,
+        restored
+    );
+    Ok(())
+}
+
+fn chunked_texture_upload() -> Result<()> {
+    let texture = Texture::<[f32; 3]>::new(
+        NonZeroU32::new(3).unwrap(),
+        NonZeroU32::new(5).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+        Dimension::D2,
+    );
+    let data = (0..3 * 5 * 3).map(|i| i as f32).collect::<Vec<_>>();
+    eyre::ensure!(
+        texture.set_data_chunked(&data, 0, |_| {}).is_err(),
+        "Empty chunks were accepted"
+    );
+    let mut progress = vec![];
+    texture.set_data_chunked(&data, 2, |p| progress.push(p))?;
+    eyre::ensure!(progress == [0.4, 0.8, 1.0], "Progress {:?}", progress);
+    let read_back = texture.mipmap(0)?.download()?;
+    eyre::ensure!(read_back == data, "Read back {:?}", read_back);
+    Ok(())
+}
+
+fn draw_auto_with_and_without_elements() -> Result<()> {
+    let program = Program::from_sources(
+        r#"#version 330 core
+layout(location = 0) in vec2 position;
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+"#,
+        WHITE_FRAGMENT_SHADER,
+        None::<&str>,
+    )?;
+    // One point on the center of each pixel of a 2x1 target
+    let vertices = ArrayBuffer::with_data(&[[-0.5f32, 0.0], [0.5, 0.0]])?;
+
+    let mut vao = VertexArray::new();
+    vao.with_vertex_buffer(&vertices)?;
+    let (_texture, fbo) = render_target(2, 1)?;
+    fbo.draw_auto(&program, &vao, DrawMode::Points, None)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 1])?;
+    eyre::ensure!(
+        pixels == [1.0; 8],
+        "Non-indexed draw read back {:?}",
+        pixels
+    );
+
+    let indices = ElementBuffer::with_data(&[1u32])?;
+    let mut indexed = VertexArray::new();
+    indexed.with_vertex_buffer(&vertices)?;
+    indexed.with_element_buffer(&indices)?;
+    let (_texture, fbo) = render_target(2, 1)?;
+    fbo.draw_auto(&program, &indexed, DrawMode::Points, None)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 2, 1])?;
+    eyre::ensure!(
+        pixels == [0.0, 0.0, 0.0, 0.0, 1.0, 1.0, 1.0, 1.0],
+        "Indexed draw read back {:?}",
+        pixels
+    );
+    Ok(())
+}
+
+fn line_width_out_of_range() -> Result<()> {
+    let (min, max) = violette::line_width_range();
+    eyre::ensure!(
+        violette::set_line_width(max + 1.0).is_err(),
+        "line width above the supported range was accepted"
+    );
+    eyre::ensure!(
+        violette::set_line_width(-1.0).is_err(),
+        "negative line width was accepted"
+    );
+    eyre::ensure!(
+        query::get_f32(gl::LINE_WIDTH) == 1.0,
+        "rejected line width changed the GL state"
+    );
+    violette::set_line_width(min)?;
+    violette::set_line_width(1.0)?;
+    Ok(())
+}
+
+fn depth_test_state_round_trip() -> Result<()> {
+    Framebuffer::enable_depth_test(DepthTestFunction::Less);
+    let enabled = Framebuffer::depth_test_state();
+    Framebuffer::disable_depth_test();
+    eyre::ensure!(
+        enabled == Some(DepthTestFunction::Less),
+        "Depth test state is {:?}",
+        enabled
+    );
+    let disabled = Framebuffer::depth_test_state();
+    eyre::ensure!(
+        disabled.is_none(),
+        "Disabled depth test reports {:?}",
+        disabled
+    );
+    Ok(())
+}
+
+fn patch_vertices() -> Result<()> {
+    violette::set_patch_vertices(4)?;
+    let vertices = query::get_i32(gl::PATCH_VERTICES);
+    eyre::ensure!(vertices == 4, "Patch vertices is {}", vertices);
+    eyre::ensure!(
+        violette::set_patch_vertices(0).is_err(),
+        "Zero patch vertices was accepted"
+    );
+    violette::set_patch_vertices(3)?;
+    Ok(())
+}
+
+fn program_pipeline_draw() -> Result<()> {
+    let vertex = Program::new_separable(ShaderStage::Vertex, FULLSCREEN_VERTEX_SHADER)?;
+    let fragment = Program::new_separable(
+        ShaderStage::Fragment,
+        r#"#version 330 core
+out vec4 color;
+void main() {
+    color = vec4(0.0, 1.0, 0.0, 1.0);
+}
+"#,
+    )?;
+    let pipeline = ProgramPipeline::new();
+    pipeline.use_stages(ProgramStages::VERTEX, &vertex)?;
+    pipeline.use_stages(ProgramStages::FRAGMENT, &fragment)?;
+    let (_texture, fbo) = render_target(1, 1)?;
+    draw_fullscreen_quad(&fbo, &pipeline)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 1, 1])?;
+    eyre::ensure!(pixels == [0.0, 1.0, 0.0, 1.0], "Pipeline drew {:?}", pixels);
+    Ok(())
+}
+
+fn sub_data_2d_exact_fit() -> Result<()> {
+    let size = NonZeroU32::new(4).unwrap();
+    let texture = Texture::<[f32; 4]>::new(size, size, NonZeroU32::new(1).unwrap(), Dimension::D2);
+    texture.set_data(&[0.0; 64])?;
+    // A rectangle touching the right and top edges fits exactly
+    texture.set_sub_data_2d(0, 2, 3, 2, 1, &[1.0; 8])?;
+    eyre::ensure!(
+        texture.set_sub_data_2d(0, 3, 0, 2, 1, &[1.0; 8]).is_err(),
+        "Rectangle one texel past the right edge was accepted"
+    );
+    eyre::ensure!(
+        texture.set_sub_data_2d(0, 0, 3, 1, 2, &[1.0; 8]).is_err(),
+        "Rectangle one texel past the top edge was accepted"
+    );
+    let read_back = texture.mipmap(0)?.download()?;
+    let texel = |x: usize, y: usize| read_back[(y * 4 + x) * 4];
+    eyre::ensure!(
+        texel(2, 3) == 1.0 && texel(3, 3) == 1.0 && texel(1, 3) == 0.0,
+        "Exact-fit upload landed elsewhere: {:?}",
+        read_back
+    );
+    Ok(())
+}
+
+#[cfg(feature = "img")]
+fn download_image_flips_rows() -> Result<()> {
+    let texture = Texture::<[u8; 4]>::new(
+        NonZeroU32::new(1).unwrap(),
+        NonZeroU32::new(2).unwrap(),
+        NonZeroU32::new(1).unwrap(),
+        Dimension::D2,
+    );
+    // OpenGL rows go bottom to top, image rows top to bottom
+    texture.set_data(&[10, 10, 10, 255, 20, 20, 20, 255])?;
+    let image = texture.mipmap(0)?.download_image::<image::Rgba<u8>>()?;
+    eyre::ensure!(
+        image.get_pixel(0, 0).0 == [20, 20, 20, 255]
+            && image.get_pixel(0, 1).0 == [10, 10, 10, 255],
+        "Image rows are not flipped: {:?}",
+        image.as_raw()
+    );
+    Ok(())
+}
+
+fn srgb_attachment_encoding() -> Result<()> {
+    let program = Program::from_sources(
+        FULLSCREEN_VERTEX_SHADER,
+        r#"#version 330 core
+out vec4 color;
+void main() {
+    color = vec4(0.5, 0.5, 0.5, 0.5);
+}
+"#,
+        None::<&str>,
+    )?;
+    let one = NonZeroU32::new(1).unwrap();
+    let texture = Texture::<Srgba8>::new(one, one, one, Dimension::D2);
+    texture.reserve_memory()?;
+    let fbo = Framebuffer::builder()
+        .with_color(0, texture.mipmap(0)?)?
+        .build()?;
+    eyre::ensure!(
+        fbo.attach_color_srgb(0, texture.mipmap(0)?, false).is_err(),
+        "sRGB texture was accepted as a linear attachment"
+    );
+    fbo.attach_color_srgb(0, texture.mipmap(0)?, true)?;
+    Framebuffer::viewport(0, 0, 1, 1);
+    let result = draw_fullscreen_quad(&fbo, &program);
+    Framebuffer::set_srgb(false);
+    result?;
+    // Linear 0.5 encodes to ~0.735 in sRGB, while alpha is stored linearly
+    let pixels = fbo.read_pixels::<Srgba8>(0, [0, 0, 1, 1])?;
+    eyre::ensure!(
+        pixels[..3].iter().all(|&c| c.abs_diff(188) <= 1) && pixels[3].abs_diff(128) <= 1,
+        "Read back {:?}",
+        pixels
+    );
+    Ok(())
+}
+
+fn render_to_3d_slices() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let texture = Texture::<[f32; 4]>::new(size, size, NonZeroU32::new(4).unwrap(), Dimension::D3);
+    texture.reserve_memory()?;
+    let fbo = Framebuffer::new();
+    for z in 0..4 {
+        fbo.attach_color_3d(0, &texture, z)?;
+        if z == 0 {
+            fbo.enable_buffers([0])?;
+            fbo.assert_complete()?;
+        }
+        fbo.clear_color_buffer(0, [z as f32, 0.0, 0.0, 1.0])?;
+    }
+    let volume = texture.mipmap(0)?.download()?;
+    eyre::ensure!(
+        volume.len() == 2 * 2 * 4 * 4,
+        "Downloaded {} values",
+        volume.len()
+    );
+    for (z, slice) in volume.chunks(2 * 2 * 4).enumerate() {
+        let expected = [z as f32, 0.0, 0.0, 1.0];
+        eyre::ensure!(
+            slice.chunks(4).all(|pixel| pixel == expected),
+            "Slice {} read back {:?}",
+            z,
+            slice
+        );
+    }
+    Ok(())
+}
+
+fn multisample_framebuffer_samples() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let fbo = MultisampleFramebuffer::<[f32; 4], DepthStencil<f32, ()>>::new(
+        size,
+        size,
+        NonZeroU32::new(4).unwrap(),
+    )?;
+    let samples = fbo.samples();
+    eyre::ensure!(samples >= 1, "Framebuffer reports {} samples", samples);
+    eyre::ensure!(
+        Framebuffer::backbuffer().samples() >= 0,
+        "Backbuffer sample count query failed"
+    );
+    Ok(())
+}
+
+fn texture_allocated_samples() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let one = NonZeroU32::new(1).unwrap();
+    let requested = NonZeroU32::new(4).unwrap();
+    let texture = Texture::<[f32; 4]>::new_multisampled(size, size, one, Dimension::D2, requested);
+    texture.reserve_memory()?;
+    let samples = texture.allocated_samples()?;
+    eyre::ensure!(
+        samples >= 1,
+        "Texture was allocated with {} samples",
+        samples
+    );
+    let single = Texture::<[f32; 4]>::new(size, size, one, Dimension::D2);
+    single.reserve_memory()?;
+    let samples = single.allocated_samples()?;
+    eyre::ensure!(
+        samples == 0,
+        "Single-sampled texture reports {} samples",
+        samples
+    );
+    Ok(())
+}
+
+fn array_buffer_slice_stride() -> Result<()> {
+    let buffer = ArrayBuffer::with_data(&[1u32, 2, 3, 4])?;
+    // Vertex buffers are tightly packed, only uniform buffers pad elements to the offset alignment
+    let slice = buffer.slice(1..3);
+    let data = slice.get_all(BufferAccess::MAP_READ)?;
+    eyre::ensure!(*data == [2, 3], "Read back {:?}", &*data);
+    Ok(())
+}
+
+fn cull_state_save_restore() -> Result<()> {
+    let saved = CullState::current();
+    let changed = CullState {
+        enabled: !saved.enabled,
+        mode: Cull::FrontAndBack,
+        front_face: match saved.front_face {
+            FrontFace::Clockwise => FrontFace::CounterClockwise,
+            FrontFace::CounterClockwise => FrontFace::Clockwise,
+        },
+    };
+    changed.apply();
+    let current = CullState::current();
+    saved.apply();
+    eyre::ensure!(
+        current == changed,
+        "Applied {:?}, got {:?}",
+        changed,
+        current
+    );
+    let restored = CullState::current();
+    eyre::ensure!(
+        restored == saved,
+        "Restored {:?}, got {:?}",
+        saved,
+        restored
+    );
+    Ok(())
+}
+
+fn depth_compare_mode_toggle() -> Result<()> {
+    let shadow = Program::from_sources(
+        FULLSCREEN_VERTEX_SHADER,
+        r#"#version 330 core
+uniform sampler2DShadow u_depth;
+out vec4 color;
+void main() {
+    color = vec4(texture(u_depth, vec3(0.5, 0.5, 0.5)));
+}
+"#,
+        None::<&str>,
+    )?;
+    let raw = Program::from_sources(
+        FULLSCREEN_VERTEX_SHADER,
+        r#"#version 330 core
+uniform sampler2D u_depth;
+out vec4 color;
+void main() {
+    color = vec4(texture(u_depth, vec2(0.5)).r);
+}
+"#,
+        None::<&str>,
+    )?;
+    let one = NonZeroU32::new(1).unwrap();
+    let depth = Texture::<DepthStencil<f32, ()>>::new(one, one, one, Dimension::D2);
+    depth.filter_min(SampleMode::Nearest)?;
+    depth.filter_mag(SampleMode::Nearest)?;
+    depth.set_data(&[0.25])?;
+    let (_texture, fbo) = render_target(1, 1)?;
+
+    // The reference 0.5 is greater than the stored 0.25
+    depth.compare_mode(Some(DepthTestFunction::Greater))?;
+    shadow.set_uniform(shadow.require_uniform("u_depth")?, depth.as_uniform(0)?)?;
+    draw_fullscreen_quad(&fbo, &shadow)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 1, 1])?;
+    eyre::ensure!(pixels == [1.0; 4], "Compared {:?}", pixels);
+
+    depth.compare_mode(None)?;
+    raw.set_uniform(raw.require_uniform("u_depth")?, depth.as_uniform(0)?)?;
+    draw_fullscreen_quad(&fbo, &raw)?;
+    let pixels = fbo.read_pixels::<[f32; 4]>(0, [0, 0, 1, 1])?;
+    eyre::ensure!(pixels == [0.25; 4], "Sampled {:?}", pixels);
+    Ok(())
+}
+
+fn vertex_array_index_type() -> Result<()> {
+    let mut vao = VertexArray::new();
+    eyre::ensure!(
+        !vao.has_elements() && vao.index_type().is_none(),
+        "New vertex array reports an element buffer"
+    );
+    let indices = ElementBuffer::with_data(&[0u16, 1, 2])?;
+    vao.with_element_buffer(&indices)?;
+    eyre::ensure!(vao.has_elements(), "Element buffer was not recorded");
+    eyre::ensure!(
+        vao.index_type() == Some(gl::UNSIGNED_SHORT),
+        "Index type is {:?}",
+        vao.index_type()
+    );
+    Ok(())
+}
+
+fn element_buffer_rejects_invalid_index_types() -> Result<()> {
+    let mut vao = VertexArray::new();
+    let floats = ElementBuffer::with_data(&[0f32, 1., 2.])?;
+    eyre::ensure!(
+        vao.with_element_buffer(&floats).is_err(),
+        "Float element buffer was accepted"
+    );
+    let pairs = ElementBuffer::with_data(&[[0u16, 1], [2, 3]])?;
+    eyre::ensure!(
+        vao.with_element_buffer(&pairs).is_err(),
+        "Multi-component element buffer was accepted"
+    );
+    eyre::ensure!(
+        !vao.has_elements() && vao.index_type().is_none(),
+        "Rejected element buffer was recorded"
+    );
+    Ok(())
+}
+
+fn integer_texture_mipmaps() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let texture = Texture::<[i32; 4]>::new(size, size, NonZeroU32::new(1).unwrap(), Dimension::D2);
+    texture.filter_min_mipmap(SampleMode::Nearest, SampleMode::Nearest)?;
+    // Uploading skips mipmap generation instead of failing on it
+    texture.set_data(&[7; 16])?;
+    eyre::ensure!(
+        texture.generate_mipmaps().is_err(),
+        "Generating mipmaps of an integer texture was accepted"
+    );
+    eyre::ensure!(texture.num_mipmaps() == 1, "Integer texture has mipmaps");
+    Ok(())
+}
+
+fn dynamic_buffer_growth() -> Result<()> {
+    let mut buffer = DynamicBuffer::<u32>::with_capacity(2)?;
+    buffer.extend(&[1, 2])?;
+    eyre::ensure!(buffer.capacity() == 2, "Capacity {}", buffer.capacity());
+    // Growing past the capacity keeps the previously appended data
+    buffer.extend(&[3, 4, 5])?;
+    eyre::ensure!(
+        buffer.len() == 5 && buffer.capacity() >= 5,
+        "Length {}, capacity {}",
+        buffer.len(),
+        buffer.capacity()
+    );
+    let slice = buffer.buffer().slice(0..5);
+    let data = slice.get_all(BufferAccess::MAP_READ)?;
+    eyre::ensure!(*data == [1, 2, 3, 4, 5], "Read back {:?}", &*data);
+    drop(data);
+
+    let capacity = buffer.capacity();
+    buffer.clear()?;
+    buffer.extend(&[9])?;
+    eyre::ensure!(
+        buffer.len() == 1 && buffer.capacity() == capacity,
+        "Length {}, capacity {}",
+        buffer.len(),
+        buffer.capacity()
+    );
+    let slice = buffer.buffer().slice(0..1);
+    let data = slice.get_all(BufferAccess::MAP_READ)?;
+    eyre::ensure!(*data == [9], "Read back {:?}", &*data);
+    Ok(())
+}
+
+fn texture_binder_units() -> Result<()> {
+    let one = NonZeroU32::new(1).unwrap();
+    let textures = (0..4)
+        .map(|_| Texture::<[f32; 4]>::new(one, one, one, Dimension::D2))
+        .collect::<Vec<_>>();
+    let mut binder = TextureBinder::new();
+    let units = textures
+        .iter()
+        .map(|texture| texture.bind_next(&mut binder))
+        .collect::<Result<Vec<_>>>()?;
+    for (i, unit) in units.iter().enumerate() {
+        eyre::ensure!(
+            !units[..i].contains(unit),
+            "Texture {} was bound to an already used unit {:?}",
+            i,
+            unit
+        );
+    }
+    // Each texture is still bound on its own unit
+    for (i, texture) in textures.iter().enumerate() {
+        let mut bound = 0;
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + i as u32);
+            gl::GetIntegerv(gl::TEXTURE_BINDING_2D, &mut bound);
+        }
+        eyre::ensure!(
+            bound as u32 == texture.id().get(),
+            "Unit {} has texture {} bound instead of {}",
+            i,
+            bound,
+            texture.id()
+        );
+    }
+    unsafe { gl::ActiveTexture(gl::TEXTURE0) };
+    binder.reset();
+    eyre::ensure!(binder.next_unit()? == 0, "Units were not released");
+    Ok(())
+}
+
+fn depth_attachments() -> Result<()> {
+    let size = NonZeroU32::new(2).unwrap();
+    let one = NonZeroU32::new(1).unwrap();
+    let color = Texture::<[f32; 4]>::new(size, size, one, Dimension::D2);
+    color.reserve_memory()?;
+    let depth = Texture::<DepthStencil<f32, ()>>::new(size, size, one, Dimension::D2);
+    depth.set_data(&[1.0; 4])?;
+    Framebuffer::builder()
+        .with_color(0, color.mipmap(0)?)?
+        .with_depth(&depth)?
+        .build()?;
+    let depth_stencil = Texture::<DepthStencil<f32, u8>>::new(size, size, one, Dimension::D2);
+    depth_stencil.set_data(
+        &[Depth32FStencil8 {
+            depth: 1.0,
+            stencil: 0,
+        }; 4],
+    )?;
+    Framebuffer::builder()
+        .with_color(0, color.mipmap(0)?)?
+        .with_depth_stencil(&depth_stencil)?
+        .build()?;
+    Ok(())
+}
+
+type Test = fn() -> Result<()>;
+
+const TESTS: &[(&str, Test)] = &[
+    ("uniform_vec3_round_trip", uniform_vec3_round_trip),
+    ("uniform_raw_round_trip", uniform_raw_round_trip),
+    ("uniform_ivec2_into_slice", uniform_ivec2_into_slice),
+    ("buffer_clear_range", buffer_clear_range),
+    ("fullscreen_quad_uvs", fullscreen_quad_uvs),
+    ("odd_width_rgb_round_trip", odd_width_rgb_round_trip),
+    ("instanced_attributes", instanced_attributes),
+    ("separate_vertex_format", separate_vertex_format),
+    ("validate_attribute_mismatch", validate_attribute_mismatch),
+    ("program_binary_round_trip", program_binary_round_trip),
+    (
+        "float_clear_of_integer_attachment",
+        float_clear_of_integer_attachment,
+    ),
+    ("persistent_mapping_flush", persistent_mapping_flush),
+    ("render_to_3d_slices", render_to_3d_slices),
+    (
+        "multisample_framebuffer_samples",
+        multisample_framebuffer_samples,
+    ),
+    ("texture_allocated_samples", texture_allocated_samples),
+    ("array_buffer_slice_stride", array_buffer_slice_stride),
+    ("cull_state_save_restore", cull_state_save_restore),
+    ("depth_compare_mode_toggle", depth_compare_mode_toggle),
+    ("vertex_array_index_type", vertex_array_index_type),
+    ("integer_texture_mipmaps", integer_texture_mipmaps),
+    ("dynamic_buffer_growth", dynamic_buffer_growth),
+    ("texture_binder_units", texture_binder_units),
+    ("depth_attachments", depth_attachments),
+    ("depth16_render_read_back", depth16_render_read_back),
+    ("attachment_color_encoding", attachment_color_encoding),
+    ("sparse_texture_commit_upload", sparse_texture_commit_upload),
+    (
+        "assert_no_error_drains_errors",
+        assert_no_error_drains_errors,
+    ),
+    ("blend_state_round_trip", blend_state_round_trip),
+    ("chunked_texture_upload", chunked_texture_upload),
+    (
+        "draw_auto_with_and_without_elements",
+        draw_auto_with_and_without_elements,
+    ),
+    ("line_width_out_of_range", line_width_out_of_range),
+    (
+        "element_buffer_rejects_invalid_index_types",
+        element_buffer_rejects_invalid_index_types,
+    ),
+    ("depth_test_state_round_trip", depth_test_state_round_trip),
+    ("patch_vertices", patch_vertices),
+    ("program_pipeline_draw", program_pipeline_draw),
+    ("sub_data_2d_exact_fit", sub_data_2d_exact_fit),
+    #[cfg(feature = "img")]
+    ("download_image_flips_rows", download_image_flips_rows),
+    ("srgb_attachment_encoding", srgb_attachment_encoding),
+];
+
+fn main() {
+    let Ok(event_loop) = std::panic::catch_unwind(EventLoop::new) else {
+        eprintln!("Skipping OpenGL tests: no display available");
+        return;
+    };
+    let context = match ContextBuilder::new()
+        .with_gl(GlRequest::Specific(Api::OpenGl, (4, 5)))
+        .with_gl_profile(GlProfile::Core)
+        .build_headless(&event_loop, PhysicalSize::new(1, 1))
+    {
+        Ok(context) => context,
+        Err(err) => {
+            eprintln!("Skipping OpenGL tests: cannot create a context: {}", err);
+            return;
+        }
+    };
+    let context = match unsafe { context.make_current() } {
+        Ok(context) => context,
+        Err((_, err)) => {
+            eprintln!(
+                "Skipping OpenGL tests: cannot make the context current: {}",
+                err
+            );
+            return;
+        }
+    };
+    violette::load_with(|name| context.get_proc_address(name));
+
+    let mut failed = 0;
+    for (name, test) in TESTS {
+        match test() {
+            Ok(()) => println!("test {} ... ok", name),
+            Err(err) => {
+                println!("test {} ... FAILED\n{:?}", name, err);
+                failed += 1;
+            }
+        }
+    }
+    println!("\n{} passed; {} failed", TESTS.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}